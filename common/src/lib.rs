@@ -0,0 +1,3 @@
+//! Helpers shared between days.
+
+pub mod records;
@@ -0,0 +1,245 @@
+//! Splitting puzzle input into records separated by blank lines, either from
+//! a `&str` or incrementally from a `BufRead`.
+
+use std::io::{self, BufRead};
+
+/// How blank lines end records.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Separator {
+    /// Every blank line ends a record, so consecutive blank lines give empty
+    /// records. There is always at least one record, even for empty input.
+    EachBlankLine,
+    /// A run of blank lines ends a record. Leading and trailing blank lines
+    /// are ignored and no record is empty.
+    BlankRuns,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Options {
+    pub separator: Separator,
+    /// Trim whitespace from both ends of each line, so lines holding only
+    /// whitespace count as blank.
+    pub trim: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            separator: Separator::BlankRuns,
+            trim: true,
+        }
+    }
+}
+
+impl Options {
+    /// Only empty lines are blank, each one ends a record, and lines are
+    /// kept as they are.
+    pub const STRICT: Options = Options {
+        separator: Separator::EachBlankLine,
+        trim: false,
+    };
+
+    /// The line to keep, or `None` if it's blank. `\r\n` endings should
+    /// already have been stripped.
+    fn line<'a>(&self, line: &'a str) -> Option<&'a str> {
+        let line = if self.trim { line.trim() } else { line };
+        if line.is_empty() {
+            None
+        } else {
+            Some(line)
+        }
+    }
+}
+
+/// Tracks the state shared by `Records` and `ReadRecords`.
+#[derive(Debug)]
+struct Splitter<T> {
+    options: Options,
+    record: Vec<T>,
+    /// Whether the last record has been yielded, for `EachBlankLine`.
+    finished: bool,
+}
+
+impl<T> Splitter<T> {
+    fn new(options: Options) -> Self {
+        Splitter {
+            options,
+            record: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Handles a blank line, returning the record it ends, if any.
+    fn blank(&mut self) -> Option<Vec<T>> {
+        if self.options.separator == Separator::BlankRuns && self.record.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.record))
+        }
+    }
+
+    /// Handles the end of the input, returning the last record, if any.
+    fn end(&mut self) -> Option<Vec<T>> {
+        if self.finished {
+            return None;
+        }
+        self.finished = true;
+        self.blank()
+    }
+}
+
+/// Yields each record of a `&str` as its lines.
+#[derive(Debug)]
+pub struct Records<'a> {
+    lines: std::str::Lines<'a>,
+    splitter: Splitter<&'a str>,
+}
+
+impl<'a> Records<'a> {
+    pub fn new(input: &'a str, options: Options) -> Self {
+        Records {
+            lines: input.lines(),
+            splitter: Splitter::new(options),
+        }
+    }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for line in &mut self.lines {
+            match self.splitter.options.line(line) {
+                Some(line) => self.splitter.record.push(line),
+                None => {
+                    if let Some(record) = self.splitter.blank() {
+                        return Some(record);
+                    }
+                }
+            }
+        }
+
+        self.splitter.end()
+    }
+}
+
+/// Yields each record of a `BufRead` as its lines, as soon as the blank line
+/// after it has been read, so only one record is held in memory at a time.
+#[derive(Debug)]
+pub struct ReadRecords<R> {
+    reader: R,
+    splitter: Splitter<String>,
+}
+
+impl<R: BufRead> ReadRecords<R> {
+    pub fn new(reader: R, options: Options) -> Self {
+        ReadRecords {
+            reader,
+            splitter: Splitter::new(options),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ReadRecords<R> {
+    type Item = io::Result<Vec<String>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = String::new();
+
+        loop {
+            buf.clear();
+            match self.reader.read_line(&mut buf) {
+                Ok(0) => return self.splitter.end().map(Ok),
+                Ok(_) => {
+                    let line = buf.strip_suffix('\n').unwrap_or(&buf);
+                    let line = line.strip_suffix('\r').unwrap_or(line);
+                    match self.splitter.options.line(line) {
+                        Some(line) => self.splitter.record.push(line.to_owned()),
+                        None => {
+                            if let Some(record) = self.splitter.blank() {
+                                return Some(Ok(record));
+                            }
+                        }
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{Cursor, Read};
+
+    const INPUT: &str = "\n\na b\r\nc \r\n\r\nd\n\n \n\t\r\n\ne\n\n";
+
+    fn records(input: &str, options: Options) -> Vec<Vec<&str>> {
+        Records::new(input, options).collect()
+    }
+
+    fn read_records(input: &str, options: Options) -> Vec<Vec<String>> {
+        ReadRecords::new(Cursor::new(input), options)
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn splits_on_blank_runs() {
+        let expected = vec![vec!["a b", "c"], vec!["d"], vec!["e"]];
+
+        assert_eq!(expected, records(INPUT, Options::default()));
+        assert_eq!(expected, read_records(INPUT, Options::default()));
+        assert!(records("", Options::default()).is_empty());
+        assert!(records("\n \r\n\n", Options::default()).is_empty());
+    }
+
+    #[test]
+    fn splits_on_each_blank_line() {
+        let expected = vec![
+            vec![],
+            vec![],
+            vec!["a b", "c "],
+            vec!["d"],
+            vec![" ", "\t"],
+            vec!["e"],
+            vec![],
+        ];
+
+        assert_eq!(expected, records(INPUT, Options::STRICT));
+        assert_eq!(expected, read_records(INPUT, Options::STRICT));
+        assert_eq!(vec![Vec::<&str>::new()], records("", Options::STRICT));
+        assert_eq!(vec![vec!["a"]], records("a\n", Options::STRICT));
+    }
+
+    #[test]
+    fn trims_without_collapsing() {
+        let options = Options {
+            separator: Separator::EachBlankLine,
+            trim: true,
+        };
+
+        assert_eq!(
+            vec![vec!["a"], vec![], vec!["b"]],
+            records(" a \n \n\nb", options)
+        );
+    }
+
+    #[test]
+    fn read_records_emits_before_end_of_input() {
+        struct Broken;
+
+        impl Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("stream broke"))
+            }
+        }
+
+        let reader = io::BufReader::new(Cursor::new("a\nb\n\n").chain(Broken));
+        let mut records = ReadRecords::new(reader, Options::default());
+
+        assert_eq!(vec!["a", "b"], records.next().unwrap().unwrap());
+        assert!(records.next().unwrap().is_err());
+    }
+}
@@ -0,0 +1,300 @@
+//! Reading and writing passport batches as text, JSON Lines, or CSV.
+
+use crate::parse_field;
+use common::records::{Options, Records};
+use serde::{
+    de::{self, MapAccess, Visitor},
+    ser::SerializeMap,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_json::Value;
+use std::{error::Error, fmt, io::Write, str::FromStr};
+
+/// Keys written first, in this order, when a batch is written as CSV.
+const CSV_KEYS: &[&str] = &["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// The puzzle's `key:value` fields, with a blank line after each passport.
+    Text,
+    /// One JSON object per line.
+    JsonLines,
+    /// One row per passport, one column per key. Repeated keys keep the last
+    /// value and empty values are written as absent.
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "jsonl" => Ok(Format::JsonLines),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("Unknown format '{}'", s)),
+        }
+    }
+}
+
+/// A passport's raw fields, in input order, before any validation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Record {
+    fields: Vec<(String, String)>,
+}
+
+impl Record {
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    fn push(&mut self, key: &str, value: &str) {
+        self.fields.push((key.to_owned(), value.to_owned()));
+    }
+}
+
+impl Serialize for Record {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.fields.len()))?;
+        for (key, value) in self.fields() {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Record {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RecordVisitor;
+
+        impl<'de> Visitor<'de> for RecordVisitor {
+            type Value = Record;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "an object of passport fields")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Record, A::Error> {
+                let mut record = Record::default();
+                while let Some((key, value)) = map.next_entry::<String, Value>()? {
+                    match value {
+                        Value::String(s) => record.push(&key, &s),
+                        Value::Number(n) => record.push(&key, &n.to_string()),
+                        v => {
+                            return Err(de::Error::custom(format!(
+                                "field {} must be a string or number, found {}",
+                                key, v
+                            )))
+                        }
+                    }
+                }
+
+                Ok(record)
+            }
+        }
+
+        deserializer.deserialize_map(RecordVisitor)
+    }
+}
+
+pub fn read(input: &str, format: Format) -> Result<Vec<Record>, Box<dyn Error>> {
+    match format {
+        Format::Text => read_text(input),
+        Format::JsonLines => read_json_lines(input),
+        Format::Csv => read_csv(input),
+    }
+}
+
+pub fn write(records: &[Record], format: Format, out: impl Write) -> Result<(), Box<dyn Error>> {
+    match format {
+        Format::Text => write_text(records, out),
+        Format::JsonLines => write_json_lines(records, out),
+        Format::Csv => write_csv(records, out),
+    }
+}
+
+fn read_text(input: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+    let mut records = Vec::new();
+    for (i, lines) in Records::new(input, Options::default()).enumerate() {
+        let mut record = Record::default();
+        for field in lines.iter().flat_map(|line| line.split_whitespace()) {
+            let (key, value) =
+                parse_field(field).map_err(|e| format!("Passport {}: {}", i + 1, e))?;
+            record.push(key, value);
+        }
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+/// Fails on a passport with no fields, which would read back as no passport
+/// at all.
+fn write_text(records: &[Record], mut out: impl Write) -> Result<(), Box<dyn Error>> {
+    for (i, record) in records.iter().enumerate() {
+        if record.fields.is_empty() {
+            return Err(format!("Passport {}: no fields to write as text", i + 1).into());
+        }
+        if i > 0 {
+            writeln!(out)?;
+        }
+        let fields: Vec<String> = record
+            .fields()
+            .map(|(k, v)| format!("{}:{}", k, v))
+            .collect();
+        writeln!(out, "{}", fields.join(" "))?;
+    }
+
+    Ok(())
+}
+
+fn read_json_lines(input: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|e| format!("Line {}: {}", i + 1, e).into())
+        })
+        .collect()
+}
+
+fn write_json_lines(records: &[Record], mut out: impl Write) -> Result<(), Box<dyn Error>> {
+    for record in records {
+        serde_json::to_writer(&mut out, record)?;
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+fn read_csv(input: &str) -> Result<Vec<Record>, Box<dyn Error>> {
+    let mut reader = csv::Reader::from_reader(input.as_bytes());
+    let headers = reader.headers()?.clone();
+
+    let mut records = Vec::new();
+    for row in reader.records() {
+        let mut record = Record::default();
+        for (key, value) in headers.iter().zip(row?.iter()) {
+            if !value.is_empty() {
+                record.push(key, value);
+            }
+        }
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+fn write_csv(records: &[Record], out: impl Write) -> Result<(), Box<dyn Error>> {
+    let mut keys: Vec<&str> = CSV_KEYS
+        .iter()
+        .copied()
+        .filter(|key| records.iter().any(|r| r.fields().any(|(k, _)| k == *key)))
+        .collect();
+    for (key, _) in records.iter().flat_map(Record::fields) {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(&keys)?;
+    for record in records {
+        let row = keys.iter().map(|key| {
+            record
+                .fields()
+                .filter(|(k, _)| k == key)
+                .last()
+                .map_or("", |(_, v)| v)
+        });
+        writer.write_record(row)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TEXT: &str = "\
+ecl:gry pid:860033327 eyr:2020 hcl:#fffffd byr:1937 iyr:2017 cid:147 hgt:183cm
+
+iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884 hcl:#cfa07d byr:1929 xyz:1
+";
+
+    fn convert(input: &str, from: Format, to: Format) -> String {
+        let records = read(input, from).unwrap();
+        let mut out = Vec::new();
+        write(&records, to, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn text_round_trips() {
+        assert_eq!(TEXT, convert(TEXT, Format::Text, Format::Text));
+
+        let jsonl = convert(TEXT, Format::Text, Format::JsonLines);
+        assert_eq!(TEXT, convert(&jsonl, Format::JsonLines, Format::Text));
+    }
+
+    #[test]
+    fn writes_json_lines() {
+        let jsonl = convert(TEXT, Format::Text, Format::JsonLines);
+        assert_eq!(
+            r##"{"iyr":"2013","ecl":"amb","cid":"350","eyr":"2023","pid":"028048884","hcl":"#cfa07d","byr":"1929","xyz":"1"}"##,
+            jsonl.lines().nth(1).unwrap()
+        );
+    }
+
+    #[test]
+    fn reads_json_numbers() {
+        let records = read(r#"{"byr":1937,"pid":"028048884"}"#, Format::JsonLines).unwrap();
+        assert_eq!(
+            vec![("byr", "1937"), ("pid", "028048884")],
+            records[0].fields().collect::<Vec<_>>()
+        );
+        assert!(read(r#"{"byr":[1937]}"#, Format::JsonLines).is_err());
+    }
+
+    #[test]
+    fn writes_csv() {
+        let csv = convert(TEXT, Format::Text, Format::Csv);
+        assert_eq!(
+            "\
+byr,iyr,eyr,hgt,hcl,ecl,pid,cid,xyz
+1937,2017,2020,183cm,#fffffd,gry,860033327,147,
+1929,2013,2023,,#cfa07d,amb,028048884,350,1
+",
+            csv
+        );
+
+        let records = read(&csv, Format::Csv).unwrap();
+        assert_eq!(None, records[1].fields().find(|(k, _)| *k == "hgt"));
+        assert_eq!(Some(("xyz", "1")), records[1].fields().last());
+    }
+
+    #[test]
+    fn empty_rows_are_kept_or_rejected() {
+        let csv = "byr,pid\n1937,860033327\n,\n1929,028048884\n";
+
+        let jsonl = convert(csv, Format::Csv, Format::JsonLines);
+        assert_eq!(Some("{}"), jsonl.lines().nth(1));
+        assert_eq!(csv, convert(&jsonl, Format::JsonLines, Format::Csv));
+
+        let records = read(csv, Format::Csv).unwrap();
+        let err = write(&records, Format::Text, Vec::new()).unwrap_err();
+        assert_eq!("Passport 2: no fields to write as text", err.to_string());
+    }
+
+    #[test]
+    fn malformed_text_is_an_error() {
+        let err = read("byr:1937\n\nhgt", Format::Text).unwrap_err();
+        assert_eq!(
+            "Passport 2: Validation Error: malformed field 'hgt'",
+            err.to_string()
+        );
+    }
+}
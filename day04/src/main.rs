@@ -1,57 +1,433 @@
 //! --- Day 4: Passport Processing ---
 //! https://adventofcode.com/2020/day/4
 
-use std::fmt::Debug;
+mod batch;
+mod stream;
+
+use batch::{Format, Record};
+use common::records::{Options, Records};
+use rayon::prelude::*;
+use std::{
+    collections::HashMap,
+    env,
+    error::Error,
+    fmt,
+    fmt::Debug,
+    fs,
+    io::{self, BufReader, Read},
+    process,
+    str::FromStr,
+};
 
 const INPUT: &str = include_str!("../../inputs/day04.txt");
 
+const USAGE: &str = "\
+Usage: day04 report [--duplicates reject|warn|first|last] [--unknown reject|warn|keep]
+                    [--format text|jsonl|csv] [--show-valid] [--parallel | --stream]
+                    [FILE]
+       day04 convert --from text|jsonl|csv --to text|jsonl|csv [INPUT [OUTPUT]]
+
+With no arguments, prints both parts for the bundled input. report reads the
+bundled input when FILE is omitted, except with --stream, which reads FILE or
+stdin one passport at a time. INPUT and OUTPUT default to stdin and stdout; '-' also means stdin or stdout.";
+
 fn main() {
-    println!("Part one: {}", validate::<PassportTypeOne>(INPUT));
-    println!("Part two: {}", validate::<PassportTypeTwo>(INPUT));
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        println!("Part one: {}", validate::<PassportTypeOne>(INPUT));
+        println!("Part two: {}", validate::<PassportTypeTwo>(INPUT));
+        return;
+    }
+
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    match args[0].as_str() {
+        "report" => {
+            let mut policy = Policy::default();
+            let mut format = Format::Text;
+            let mut show_valid = false;
+            let mut parallel = false;
+            let mut streaming = false;
+            let mut paths = Vec::new();
+            let mut iter = args[1..].iter();
+            while let Some(arg) = iter.next() {
+                match arg.as_str() {
+                    "--duplicates" => policy.duplicate = next_arg(&mut iter, arg)?.parse()?,
+                    "--unknown" => policy.unknown = next_arg(&mut iter, arg)?.parse()?,
+                    "--format" => format = next_arg(&mut iter, arg)?.parse()?,
+                    "--show-valid" => show_valid = true,
+                    "--parallel" => parallel = true,
+                    "--stream" => streaming = true,
+                    _ => paths.push(positional(arg, paths.len(), 1)?),
+                }
+            }
+            let path = paths.first().copied();
+
+            if streaming {
+                if format != Format::Text || parallel {
+                    return Err("--stream only supports text input without --parallel".into());
+                }
+                return match path {
+                    Some(path) if path != "-" => {
+                        let reader = BufReader::new(fs::File::open(path)?);
+                        report(
+                            stream::check_reader::<PassportTypeTwo>(reader, policy),
+                            show_valid,
+                        )
+                    }
+                    _ => report(
+                        stream::check_reader::<PassportTypeTwo>(io::stdin().lock(), policy),
+                        show_valid,
+                    ),
+                };
+            }
+
+            let input = match path {
+                Some(path) => fs::read_to_string(path)?,
+                None => INPUT.to_owned(),
+            };
+            let checked = match format {
+                Format::Text if parallel => check_with_par::<PassportTypeTwo>(&input, policy),
+                Format::Text => check_with::<PassportTypeTwo>(&input, policy),
+                format if parallel => {
+                    check_batch_par::<PassportTypeTwo>(&batch::read(&input, format)?, policy)
+                }
+                format => check_batch::<PassportTypeTwo>(&batch::read(&input, format)?, policy),
+            };
+
+            report(checked.into_iter().map(Ok), show_valid)
+        }
+        "convert" => {
+            let mut from = None;
+            let mut to = None;
+            let mut paths = Vec::new();
+            let mut iter = args[1..].iter();
+            while let Some(arg) = iter.next() {
+                match arg.as_str() {
+                    "--from" => from = Some(next_arg(&mut iter, arg)?.parse::<Format>()?),
+                    "--to" => to = Some(next_arg(&mut iter, arg)?.parse::<Format>()?),
+                    _ => paths.push(positional(arg, paths.len(), 2)?),
+                }
+            }
+            let from = from.ok_or("Missing --from")?;
+            let to = to.ok_or("Missing --to")?;
+
+            let input = match paths.first() {
+                Some(&path) if path != "-" => fs::read_to_string(path)?,
+                _ => {
+                    let mut input = String::new();
+                    io::stdin().read_to_string(&mut input)?;
+                    input
+                }
+            };
+            let records = batch::read(&input, from)?;
+
+            match paths.get(1) {
+                Some(&path) if path != "-" => batch::write(&records, to, fs::File::create(path)?)?,
+                _ => batch::write(&records, to, io::stdout().lock())?,
+            }
+
+            Ok(())
+        }
+        cmd => Err(format!("Unknown command '{}'", cmd).into()),
+    }
+}
+
+fn next_arg<'a>(
+    iter: &mut impl Iterator<Item = &'a String>,
+    flag: &str,
+) -> Result<&'a str, Box<dyn Error>> {
+    iter.next()
+        .map(String::as_str)
+        .ok_or_else(|| format!("Missing value for {}", flag).into())
+}
+
+/// An argument that isn't a known flag, if there's room for another one.
+fn positional(arg: &str, taken: usize, max: usize) -> Result<&str, Box<dyn Error>> {
+    if arg.starts_with("--") {
+        Err(format!("Unknown flag '{}'", arg).into())
+    } else if taken >= max {
+        Err(format!("Unexpected argument '{}'", arg).into())
+    } else {
+        Ok(arg)
+    }
+}
+
+fn report(
+    checked: impl Iterator<Item = io::Result<Checked<ValidatedPassport>>>,
+    show_valid: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut valid = 0;
+    let mut total = 0;
+
+    for (i, passport) in checked.enumerate() {
+        let passport = passport?;
+        total += 1;
+        for warning in passport.warnings.iter() {
+            println!("Passport {}: warning: {}", i + 1, warning);
+        }
+        match passport.result {
+            Ok(validated) => {
+                valid += 1;
+                if show_valid {
+                    println!("Passport {}: {}", i + 1, validated);
+                }
+            }
+            Err(e) => println!("Passport {}: {}", i + 1, e),
+        }
+    }
+
+    println!("{} of {} passports valid", valid, total);
+
+    Ok(())
 }
 
 fn validate<T: Passport + Debug + Default>(input: &str) -> u64 {
-    let mut count = 0;
+    check::<T>(input).iter().filter(|res| res.is_ok()).count() as u64
+}
+
+fn check<T: Passport + Debug + Default>(input: &str) -> Vec<Result<T::Valid, ValidationError>> {
+    check_with::<T>(input, Policy::default())
+        .into_iter()
+        .map(|checked| checked.result)
+        .collect()
+}
+
+fn check_with<T: Passport + Debug + Default>(
+    input: &str,
+    policy: Policy,
+) -> Vec<Checked<T::Valid>> {
+    Records::new(input, Options::default())
+        .map(|lines| check_block::<T>(&lines, policy))
+        .collect()
+}
+
+/// Same as `check_with`, but validates the passports on rayon's thread pool.
+fn check_with_par<T: Passport + Debug + Default>(
+    input: &str,
+    policy: Policy,
+) -> Vec<Checked<T::Valid>>
+where
+    T::Valid: Send,
+{
+    let records: Vec<Vec<&str>> = Records::new(input, Options::default()).collect();
+    records
+        .par_iter()
+        .map(|lines| check_block::<T>(lines, policy))
+        .collect()
+}
+
+fn check_block<T: Passport + Debug + Default>(
+    lines: &[impl AsRef<str>],
+    policy: Policy,
+) -> Checked<T::Valid> {
+    let fields = lines
+        .iter()
+        .flat_map(|line| line.as_ref().split_whitespace());
+    check_fields::<T>(fields.map(parse_field), policy)
+}
+
+fn check_batch<T: Passport + Debug + Default>(
+    records: &[Record],
+    policy: Policy,
+) -> Vec<Checked<T::Valid>> {
+    records
+        .iter()
+        .map(|record| check_fields::<T>(record.fields().map(Ok), policy))
+        .collect()
+}
+
+/// Same as `check_batch`, but validates the passports on rayon's thread pool.
+fn check_batch_par<T: Passport + Debug + Default>(
+    records: &[Record],
+    policy: Policy,
+) -> Vec<Checked<T::Valid>>
+where
+    T::Valid: Send,
+{
+    records
+        .par_iter()
+        .map(|record| check_fields::<T>(record.fields().map(Ok), policy))
+        .collect()
+}
+
+fn check_fields<'a, T: Passport + Debug + Default>(
+    fields: impl Iterator<Item = Result<(&'a str, &'a str), ValidationError>>,
+    policy: Policy,
+) -> Checked<T::Valid> {
     let mut passport = T::default();
+    let mut error = None;
+    let mut warnings = Vec::new();
 
-    for line in input.lines() {
-        if line == "" {
-            if passport.is_valid() {
-                count += 1;
-            }
-            passport = T::default();
-            continue;
+    for field in fields {
+        let res = field.and_then(|(key, value)| match field_mut(&mut passport, key) {
+            Some(slot) => policy.duplicate.apply(slot, key, value, &mut warnings),
+            None => policy.unknown.apply(key, &mut warnings).and_then(|_| {
+                let extras = passport.extras_mut();
+                let mut slot = extras.get(key).cloned();
+                policy
+                    .duplicate
+                    .apply(&mut slot, key, value, &mut warnings)?;
+                if let Some(value) = slot {
+                    extras.insert(key.to_owned(), value);
+                }
+                Ok(())
+            }),
+        });
+
+        if let Err(e) = res {
+            error.get_or_insert(e);
+        }
+    }
+
+    Checked {
+        result: finish(passport, error),
+        warnings,
+    }
+}
+
+fn parse_field(field: &str) -> Result<(&str, &str), ValidationError> {
+    field
+        .split_once(':')
+        .ok_or_else(|| ValidationError::Malformed(field.to_owned()))
+}
+
+fn field_mut<'a, T: Passport>(passport: &'a mut T, key: &str) -> Option<&'a mut Option<String>> {
+    match key {
+        "byr" => Some(passport.birth_year_mut()),
+        "iyr" => Some(passport.issue_year_mut()),
+        "eyr" => Some(passport.expiration_year_mut()),
+        "hgt" => Some(passport.height_mut()),
+        "hcl" => Some(passport.hair_color_mut()),
+        "ecl" => Some(passport.eye_color_mut()),
+        "pid" => Some(passport.passport_id_mut()),
+        "cid" => Some(passport.country_id_mut()),
+        _ => None,
+    }
+}
+
+fn finish<T: Passport>(
+    passport: T,
+    error: Option<ValidationError>,
+) -> Result<T::Valid, ValidationError> {
+    match error {
+        Some(e) => Err(e),
+        None => passport.check(),
+    }
+}
+
+fn required<'a>(key: &'static str, field: &'a Option<String>) -> Result<&'a str, ValidationError> {
+    field.as_deref().ok_or(ValidationError::Missing(key))
+}
+
+#[derive(Debug)]
+struct Checked<T> {
+    result: Result<T, ValidationError>,
+    warnings: Vec<ValidationError>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Policy {
+    duplicate: DuplicatePolicy,
+    unknown: UnknownPolicy,
+}
+
+/// What to do when a passport repeats a key.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum DuplicatePolicy {
+    Reject,
+    /// Keep the last value, but record a warning.
+    Warn,
+    KeepFirst,
+    #[default]
+    KeepLast,
+}
+
+impl FromStr for DuplicatePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(DuplicatePolicy::Reject),
+            "warn" => Ok(DuplicatePolicy::Warn),
+            "first" => Ok(DuplicatePolicy::KeepFirst),
+            "last" => Ok(DuplicatePolicy::KeepLast),
+            _ => Err(format!("Unknown duplicate policy '{}'", s)),
         }
+    }
+}
+
+impl DuplicatePolicy {
+    fn apply(
+        self,
+        slot: &mut Option<String>,
+        key: &str,
+        value: &str,
+        warnings: &mut Vec<ValidationError>,
+    ) -> Result<(), ValidationError> {
+        use DuplicatePolicy::*;
 
-        for field in line.split(' ') {
-            let field = field.trim();
-            let mut kv = field.split(':');
-            let key = kv.next().unwrap();
-            let value = kv.next().unwrap();
-
-            match key {
-                "byr" => *passport.birth_year_mut() = Some(value.to_owned()),
-                "iyr" => *passport.issue_year_mut() = Some(value.to_owned()),
-                "eyr" => *passport.expiration_year_mut() = Some(value.to_owned()),
-                "hgt" => *passport.height_mut() = Some(value.to_owned()),
-                "hcl" => *passport.hair_color_mut() = Some(value.to_owned()),
-                "ecl" => *passport.eye_color_mut() = Some(value.to_owned()),
-                "pid" => *passport.passport_id_mut() = Some(value.to_owned()),
-                "cid" => *passport.country_id_mut() = Some(value.to_owned()),
-                _ => (),
+        if slot.is_some() {
+            match self {
+                Reject => return Err(ValidationError::Duplicate(key.to_owned())),
+                Warn => warnings.push(ValidationError::Duplicate(key.to_owned())),
+                KeepFirst => return Ok(()),
+                KeepLast => (),
             }
         }
+        *slot = Some(value.to_owned());
+
+        Ok(())
     }
+}
+
+/// What to do when a passport has a key we don't recognise. Unless rejected,
+/// the field is kept in the passport's extras.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum UnknownPolicy {
+    Reject,
+    Warn,
+    #[default]
+    Keep,
+}
 
-    if passport.is_valid() {
-        count += 1;
+impl FromStr for UnknownPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(UnknownPolicy::Reject),
+            "warn" => Ok(UnknownPolicy::Warn),
+            "keep" => Ok(UnknownPolicy::Keep),
+            _ => Err(format!("Unknown field policy '{}'", s)),
+        }
     }
+}
 
-    count
+impl UnknownPolicy {
+    fn apply(self, key: &str, warnings: &mut Vec<ValidationError>) -> Result<(), ValidationError> {
+        match self {
+            UnknownPolicy::Reject => Err(ValidationError::Unknown(key.to_owned())),
+            UnknownPolicy::Warn => {
+                warnings.push(ValidationError::Unknown(key.to_owned()));
+                Ok(())
+            }
+            UnknownPolicy::Keep => Ok(()),
+        }
+    }
 }
 
 trait Passport: Default {
-    fn is_valid(&self) -> bool;
+    /// What a passport becomes once it passes `check`.
+    type Valid;
+
+    fn check(self) -> Result<Self::Valid, ValidationError>;
     fn birth_year_mut(&mut self) -> &mut Option<String>;
     fn issue_year_mut(&mut self) -> &mut Option<String>;
     fn expiration_year_mut(&mut self) -> &mut Option<String>;
@@ -60,6 +436,7 @@ trait Passport: Default {
     fn eye_color_mut(&mut self) -> &mut Option<String>;
     fn passport_id_mut(&mut self) -> &mut Option<String>;
     fn country_id_mut(&mut self) -> &mut Option<String>;
+    fn extras_mut(&mut self) -> &mut HashMap<String, String>;
 }
 
 #[derive(Debug, Default)]
@@ -72,17 +449,22 @@ struct PassportTypeOne {
     eye_color: Option<String>,
     passport_id: Option<String>,
     country_id: Option<String>,
+    extras: HashMap<String, String>,
 }
 
 impl Passport for PassportTypeOne {
-    fn is_valid(&self) -> bool {
-        self.birth_year.is_some()
-            && self.issue_year.is_some()
-            && self.expiration_year.is_some()
-            && self.height.is_some()
-            && self.hair_color.is_some()
-            && self.eye_color.is_some()
-            && self.passport_id.is_some()
+    type Valid = Self;
+
+    fn check(self) -> Result<Self, ValidationError> {
+        required("byr", &self.birth_year)?;
+        required("iyr", &self.issue_year)?;
+        required("eyr", &self.expiration_year)?;
+        required("hgt", &self.height)?;
+        required("hcl", &self.hair_color)?;
+        required("ecl", &self.eye_color)?;
+        required("pid", &self.passport_id)?;
+
+        Ok(self)
     }
 
     fn birth_year_mut(&mut self) -> &mut Option<String> {
@@ -116,6 +498,10 @@ impl Passport for PassportTypeOne {
     fn country_id_mut(&mut self) -> &mut Option<String> {
         &mut self.country_id
     }
+
+    fn extras_mut(&mut self) -> &mut HashMap<String, String> {
+        &mut self.extras
+    }
 }
 
 #[derive(Debug, Default)]
@@ -128,17 +514,14 @@ struct PassportTypeTwo {
     eye_color: Option<String>,
     passport_id: Option<String>,
     country_id: Option<String>,
+    extras: HashMap<String, String>,
 }
 
 impl Passport for PassportTypeTwo {
-    fn is_valid(&self) -> bool {
-        self.valid_birth_year()
-            && self.valid_issue_year()
-            && self.valid_expiration_year()
-            && self.valid_height()
-            && self.valid_hair_color()
-            && self.valid_eye_color()
-            && self.valid_passport_id()
+    type Valid = ValidatedPassport;
+
+    fn check(self) -> Result<ValidatedPassport, ValidationError> {
+        self.validated()
     }
 
     fn birth_year_mut(&mut self) -> &mut Option<String> {
@@ -172,84 +555,247 @@ impl Passport for PassportTypeTwo {
     fn country_id_mut(&mut self) -> &mut Option<String> {
         &mut self.country_id
     }
+
+    fn extras_mut(&mut self) -> &mut HashMap<String, String> {
+        &mut self.extras
+    }
 }
 
 impl PassportTypeTwo {
-    fn valid_birth_year(&self) -> bool {
-        Self::valid_year(&self.birth_year, 1920, 2002)
+    fn validated(self) -> Result<ValidatedPassport, ValidationError> {
+        Ok(ValidatedPassport {
+            birth_year: Self::year("byr", &self.birth_year, 1920, 2002)?,
+            issue_year: Self::year("iyr", &self.issue_year, 2010, 2020)?,
+            expiration_year: Self::year("eyr", &self.expiration_year, 2020, 2030)?,
+            height: self.height()?,
+            hair_color: self.hair_color()?,
+            eye_color: self.eye_color()?,
+            passport_id: self.passport_id()?,
+            country_id: self.country_id,
+            extras: self.extras,
+        })
     }
 
-    fn valid_issue_year(&self) -> bool {
-        Self::valid_year(&self.issue_year, 2010, 2020)
+    fn height(&self) -> Result<Height, ValidationError> {
+        let height = required("hgt", &self.height)?;
+        let (value, unit, min, max) = if let Some(h) = height.strip_suffix("cm") {
+            (h, HeightUnit::Cm, 150, 193)
+        } else if let Some(h) = height.strip_suffix("in") {
+            (h, HeightUnit::In, 59, 76)
+        } else {
+            return Err(ValidationError::invalid("hgt", height));
+        };
+
+        match value.parse::<u16>() {
+            Ok(value) if (min..=max).contains(&value) => Ok(Height { value, unit }),
+            _ => Err(ValidationError::invalid("hgt", height)),
+        }
     }
 
-    fn valid_expiration_year(&self) -> bool {
-        Self::valid_year(&self.expiration_year, 2020, 2030)
+    fn hair_color(&self) -> Result<Rgb, ValidationError> {
+        let color = required("hcl", &self.hair_color)?;
+        let invalid = || ValidationError::invalid("hcl", color);
+        let hex = color.strip_prefix('#').ok_or_else(invalid)?;
+        if hex.len() != 6 || !hex.chars().all(|c| matches!(c, 'a'..='f' | '0'..='9')) {
+            return Err(invalid());
+        }
+        let channel = |i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+
+        Ok(Rgb {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        })
     }
 
-    fn valid_height(&self) -> bool {
-        if let Some(height) = &self.height {
-            match height.split_at(height.len() - 2) {
-                (h, "cm") => {
-                    let h = h.parse::<u8>().unwrap();
-                    h >= 150 && h <= 193
-                }
-                (h, "in") => {
-                    let h = h.parse::<u8>().unwrap();
-                    h >= 59 && h <= 76
-                }
-                _ => false,
-            }
-        } else {
-            false
+    fn eye_color(&self) -> Result<EyeColor, ValidationError> {
+        use EyeColor::*;
+
+        let color = required("ecl", &self.eye_color)?;
+        match color {
+            "amb" => Ok(Amber),
+            "blu" => Ok(Blue),
+            "brn" => Ok(Brown),
+            "gry" => Ok(Gray),
+            "grn" => Ok(Green),
+            "hzl" => Ok(Hazel),
+            "oth" => Ok(Other),
+            _ => Err(ValidationError::invalid("ecl", color)),
         }
     }
 
-    fn valid_hair_color(&self) -> bool {
-        if let Some(color) = &self.hair_color {
-            let mut chars = color.chars();
-            if chars.next().unwrap() != '#' {
-                return false;
-            }
-            chars.all(|c| matches!(c, 'a'..='f' | '0'..='9'))
+    fn passport_id(&self) -> Result<PassportId, ValidationError> {
+        let id = required("pid", &self.passport_id)?;
+        if id.len() == 9 && id.chars().all(|d| d.is_ascii_digit()) {
+            Ok(PassportId(id.to_owned()))
         } else {
-            false
+            Err(ValidationError::invalid("pid", id))
         }
     }
 
-    fn valid_eye_color(&self) -> bool {
-        if let Some(color) = &self.eye_color {
-            matches!(
-                color.as_str(),
-                "amb" | "blu" | "brn" | "gry" | "grn" | "hzl" | "oth"
-            )
-        } else {
-            false
+    fn year(
+        key: &'static str,
+        year: &Option<String>,
+        min: u16,
+        max: u16,
+    ) -> Result<u16, ValidationError> {
+        let year = required(key, year)?;
+        match year.parse::<u16>() {
+            Ok(y) if (min..=max).contains(&y) => Ok(y),
+            _ => Err(ValidationError::invalid(key, year)),
         }
     }
+}
 
-    fn valid_passport_id(&self) -> bool {
-        if let Some(id) = &self.passport_id {
-            if id.chars().count() != 9 {
-                return false;
-            }
+/// A passport whose fields have passed the part two rules, already parsed.
+#[derive(Debug, Clone, PartialEq)]
+struct ValidatedPassport {
+    birth_year: u16,
+    issue_year: u16,
+    expiration_year: u16,
+    height: Height,
+    hair_color: Rgb,
+    eye_color: EyeColor,
+    passport_id: PassportId,
+    country_id: Option<String>,
+    extras: HashMap<String, String>,
+}
 
-            id.chars().all(|d| matches!(d, '0'..='9'))
-        } else {
-            false
+impl fmt::Display for ValidatedPassport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "byr:{} iyr:{} eyr:{} hgt:{} hcl:{} ecl:{} pid:{}",
+            self.birth_year,
+            self.issue_year,
+            self.expiration_year,
+            self.height,
+            self.hair_color,
+            self.eye_color,
+            self.passport_id
+        )?;
+        if let Some(cid) = &self.country_id {
+            write!(f, " cid:{}", cid)?;
         }
+
+        Ok(())
     }
+}
 
-    fn valid_year(year: &Option<String>, min: u16, max: u16) -> bool {
-        if let Some(year) = &year {
-            let year = year.parse::<u16>().unwrap();
-            year >= min && year <= max
-        } else {
-            false
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Height {
+    value: u16,
+    unit: HeightUnit,
+}
+
+impl fmt::Display for Height {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.value, self.unit)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum HeightUnit {
+    Cm,
+    In,
+}
+
+impl fmt::Display for HeightUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeightUnit::Cm => write!(f, "cm"),
+            HeightUnit::In => write!(f, "in"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rgb {
+    r: u8,
+    g: u8,
+    b: u8,
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EyeColor {
+    Amber,
+    Blue,
+    Brown,
+    Gray,
+    Green,
+    Hazel,
+    Other,
+}
+
+impl fmt::Display for EyeColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use EyeColor::*;
+
+        let s = match self {
+            Amber => "amb",
+            Blue => "blu",
+            Brown => "brn",
+            Gray => "gry",
+            Green => "grn",
+            Hazel => "hzl",
+            Other => "oth",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Nine digits, leading zeros included.
+#[derive(Debug, Clone, PartialEq)]
+struct PassportId(String);
+
+impl fmt::Display for PassportId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ValidationError {
+    Malformed(String),
+    Duplicate(String),
+    Unknown(String),
+    Missing(&'static str),
+    Invalid { key: &'static str, value: String },
+}
+
+impl ValidationError {
+    fn invalid(key: &'static str, value: &str) -> Self {
+        ValidationError::Invalid {
+            key,
+            value: value.to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ValidationError::*;
+
+        match self {
+            Malformed(s) => write!(f, "Validation Error: malformed field '{}'", s),
+            Duplicate(k) => write!(f, "Validation Error: duplicate field {}", k),
+            Unknown(k) => write!(f, "Validation Error: unknown field {}", k),
+            Missing(k) => write!(f, "Validation Error: missing field {}", k),
+            Invalid { key, value } => {
+                write!(f, "Validation Error: invalid {} '{}'", key, value)
+            }
         }
     }
 }
 
+impl Error for ValidationError {}
+
 #[cfg(test)]
 mod test {
     use crate::*;
@@ -312,4 +858,267 @@ iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719";
         assert_eq!(0, invalid_res);
         assert_eq!(4, valid_res);
     }
+
+    #[test]
+    fn reports_reasons() {
+        let errors: Vec<_> = check::<PassportTypeTwo>(INVALID_INPUT)
+            .into_iter()
+            .map(|res| res.unwrap_err())
+            .collect();
+
+        assert_eq!(
+            vec![
+                ValidationError::invalid("eyr", "1972"),
+                ValidationError::invalid("eyr", "1967"),
+                ValidationError::invalid("hcl", "dab227"),
+                ValidationError::invalid("byr", "2007"),
+            ],
+            errors
+        );
+    }
+
+    #[test]
+    fn bad_values_are_invalid() {
+        let valid = "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f";
+        let cases = [
+            ("hgt", "300cm"),
+            ("hgt", "abccm"),
+            ("hgt", "m"),
+            ("hgt", ""),
+            ("hgt", "1\u{e9}"),
+            ("byr", "nineteen"),
+            ("byr", "99999999"),
+            ("hcl", ""),
+            ("hcl", "#abc"),
+            (
+                "pid",
+                "\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}",
+            ),
+        ];
+
+        assert!(check::<PassportTypeTwo>(valid)[0].is_ok());
+        for (key, value) in cases.iter() {
+            let input = format!("{} {}:{}", valid, key, value);
+            let res = check::<PassportTypeTwo>(&input);
+            assert_eq!(
+                Some(&ValidationError::invalid(key, value)),
+                res[0].as_ref().err()
+            );
+        }
+    }
+
+    #[test]
+    fn field_without_colon_is_malformed() {
+        let input = "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd\nbyr:1937 iyr:2017 hgt183cm";
+        let res = check::<PassportTypeOne>(input);
+        assert_eq!(
+            Some(&ValidationError::Malformed("hgt183cm".into())),
+            res[0].as_ref().err()
+        );
+    }
+
+    #[test]
+    fn validate_never_panics() {
+        const TOKENS: &[&str] = &[
+            "byr",
+            "iyr",
+            "eyr",
+            "hgt",
+            "hcl",
+            "ecl",
+            "pid",
+            "cid",
+            "xyz",
+            ":",
+            "::",
+            " ",
+            "  ",
+            "\n",
+            "\n\n",
+            "\r\n",
+            "#",
+            "cm",
+            "in",
+            "c",
+            "0",
+            "1",
+            "9",
+            "150",
+            "300",
+            "99999999999",
+            "-",
+            "a",
+            "f",
+            "z",
+            "amb",
+            "\u{e9}",
+            "\u{1f600}",
+            "\t",
+        ];
+
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for _ in 0..20_000 {
+            let len = (next() % 40) as usize;
+            let input: String = (0..len)
+                .map(|_| TOKENS[(next() % TOKENS.len() as u64) as usize])
+                .collect();
+
+            validate::<PassportTypeOne>(&input);
+            validate::<PassportTypeTwo>(&input);
+        }
+    }
+
+    const MESSY_INPUT: &str = "\
+pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980
+hcl:#623a2f byr:1850 xyz:1 xyz:2";
+
+    fn check_messy(
+        duplicate: DuplicatePolicy,
+        unknown: UnknownPolicy,
+    ) -> Checked<ValidatedPassport> {
+        let policy = Policy { duplicate, unknown };
+        check_with::<PassportTypeTwo>(MESSY_INPUT, policy).remove(0)
+    }
+
+    #[test]
+    fn duplicate_policy() {
+        use DuplicatePolicy::*;
+
+        let res = check_messy(Reject, UnknownPolicy::Keep);
+        assert_eq!(
+            Some(&ValidationError::Duplicate("byr".into())),
+            res.result.as_ref().err()
+        );
+
+        let res = check_messy(Warn, UnknownPolicy::Keep);
+        assert_eq!(
+            Some(&ValidationError::invalid("byr", "1850")),
+            res.result.as_ref().err()
+        );
+        assert_eq!(
+            vec![
+                ValidationError::Duplicate("byr".into()),
+                ValidationError::Duplicate("xyz".into())
+            ],
+            res.warnings
+        );
+
+        let res = check_messy(KeepFirst, UnknownPolicy::Keep);
+        let passport = res.result.unwrap();
+        assert_eq!(1980, passport.birth_year);
+        assert_eq!(Some("1"), passport.extras.get("xyz").map(String::as_str));
+
+        let res = check_messy(KeepLast, UnknownPolicy::Keep);
+        assert!(res.result.is_err());
+        assert!(res.warnings.is_empty());
+    }
+
+    #[test]
+    fn unknown_policy() {
+        use UnknownPolicy::*;
+
+        let res = check_messy(DuplicatePolicy::KeepFirst, Reject);
+        assert_eq!(
+            Some(&ValidationError::Unknown("xyz".into())),
+            res.result.as_ref().err()
+        );
+
+        let res = check_messy(DuplicatePolicy::KeepFirst, Warn);
+        assert!(res.result.is_ok());
+        assert_eq!(
+            vec![
+                ValidationError::Unknown("xyz".into()),
+                ValidationError::Unknown("xyz".into())
+            ],
+            res.warnings
+        );
+
+        let res = check_messy(DuplicatePolicy::KeepLast, Keep);
+        let passport = res.result.unwrap_err();
+        assert_eq!(ValidationError::invalid("byr", "1850"), passport);
+    }
+
+    #[test]
+    fn validated_passport_is_typed() {
+        let input = "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f cid:5";
+        let passport = check::<PassportTypeTwo>(input).remove(0).unwrap();
+
+        assert_eq!(
+            ValidatedPassport {
+                birth_year: 1980,
+                issue_year: 2012,
+                expiration_year: 2030,
+                height: Height {
+                    value: 74,
+                    unit: HeightUnit::In
+                },
+                hair_color: Rgb {
+                    r: 0x62,
+                    g: 0x3a,
+                    b: 0x2f
+                },
+                eye_color: EyeColor::Green,
+                passport_id: PassportId("087499704".into()),
+                country_id: Some("5".into()),
+                extras: HashMap::new(),
+            },
+            passport
+        );
+        assert_eq!(
+            "byr:1980 iyr:2012 eyr:2030 hgt:74in hcl:#623a2f ecl:grn pid:087499704 cid:5",
+            passport.to_string()
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_arguments() {
+        let err = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            run(&args).unwrap_err().to_string()
+        };
+
+        assert_eq!(
+            "Unknown flag '--show-vaild'",
+            err(&["report", "--show-vaild"])
+        );
+        assert_eq!("Unexpected argument 'b'", err(&["report", "a", "b"]));
+        assert_eq!(
+            "Unexpected argument 'c'",
+            err(&["convert", "--from", "text", "--to", "csv", "a", "b", "c"])
+        );
+        assert_eq!(
+            "Unknown flag '--form'",
+            err(&["convert", "--form", "text", "--to", "csv"])
+        );
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        for input in [INPUT, VALID_INPUT, INVALID_INPUT, crate::INPUT].iter() {
+            let policy = Policy {
+                duplicate: DuplicatePolicy::Warn,
+                unknown: UnknownPolicy::Warn,
+            };
+            let seq = check_with::<PassportTypeTwo>(input, policy);
+            let par = check_with_par::<PassportTypeTwo>(input, policy);
+            let records = batch::read(input, Format::Text).unwrap();
+            let batch_par = check_batch_par::<PassportTypeTwo>(&records, policy);
+
+            assert_eq!(seq.len(), par.len());
+            assert_eq!(seq.len(), batch_par.len());
+            for ((s, p), b) in seq.iter().zip(par.iter()).zip(batch_par.iter()) {
+                assert_eq!(s.result, p.result);
+                assert_eq!(s.result, b.result);
+                assert_eq!(s.warnings, p.warnings);
+                assert_eq!(s.warnings, b.warnings);
+            }
+        }
+    }
 }
@@ -0,0 +1,32 @@
+//! Reading passports incrementally from any `BufRead`.
+
+use crate::{check_block, Checked, Passport, Policy};
+use common::records::{Options, ReadRecords};
+use std::{fmt::Debug, io, io::BufRead};
+
+/// Validates each passport as soon as the blank line after it has been read.
+pub fn check_reader<T: Passport + Debug + Default>(
+    reader: impl BufRead,
+    policy: Policy,
+) -> impl Iterator<Item = io::Result<Checked<T::Valid>>> {
+    ReadRecords::new(reader, Options::default())
+        .map(move |lines| lines.map(|lines| check_block::<T>(&lines, policy)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{validate, PassportTypeTwo, ValidatedPassport, INPUT};
+    use std::io::Cursor;
+
+    #[test]
+    fn matches_validate() {
+        let checked: Vec<Checked<ValidatedPassport>> =
+            check_reader::<PassportTypeTwo>(Cursor::new(INPUT), Policy::default())
+                .map(Result::unwrap)
+                .collect();
+        let valid = checked.iter().filter(|c| c.result.is_ok()).count() as u64;
+
+        assert_eq!(validate::<PassportTypeTwo>(INPUT), valid);
+    }
+}
@@ -1,98 +1,398 @@
 //! --- Day 5: Binary Boarding ---
 //! https://adventofcode.com/2020/day/5
 
-use std::collections::HashSet;
+use std::{env, error::Error, fmt, fs, process, str::FromStr};
 
 const INPUT: &str = include_str!("../../inputs/day05.txt");
 
+const USAGE: &str = "\
+Usage: day05 encode SEAT_ID
+       day05 encode ROW COL
+       day05 decode PASS
+       day05 map [FILE]
+
+With no arguments, prints both parts for the bundled input.";
+
 fn main() {
-    println!("Part one: {}", part_one(INPUT));
-    println!("Part two: {}", part_two(INPUT));
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        report_errors(&SeatMap::new(Layout::STANDARD, INPUT));
+        println!("Part one: {}", part_one(INPUT));
+        match part_two(INPUT) {
+            Some(seat) => println!("Part two: {}", seat),
+            None => println!("Part two: can't find your seat"),
+        }
+        return;
+    }
+
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let layout = Layout::STANDARD;
+    match args[0].as_str() {
+        "encode" => {
+            let pass = match &args[1..] {
+                [id] => layout.encode_id(id.parse()?),
+                [row, col] => layout.encode(Seat {
+                    row: row.parse()?,
+                    col: col.parse()?,
+                }),
+                _ => return Err("encode takes a seat ID, or a row and column".into()),
+            };
+            println!("{}", pass?);
+
+            Ok(())
+        }
+        "decode" => {
+            let pass = layout.parse(args.get(1).ok_or("decode takes a boarding pass")?)?;
+            println!(
+                "row {}, column {}, seat ID {}",
+                pass.seat.row, pass.seat.col, pass.id
+            );
+
+            Ok(())
+        }
+        "map" => {
+            let input = match args.get(1) {
+                Some(path) => fs::read_to_string(path)?,
+                None => INPUT.to_owned(),
+            };
+            let map = SeatMap::new(layout, &input);
+            report_errors(&map);
+            print!("{}", map.render());
+
+            let gaps: Vec<String> = map.gaps().iter().map(u64::to_string).collect();
+            println!("Empty seats between taken seats: {}", gaps.join(", "));
+
+            Ok(())
+        }
+        cmd => Err(format!("Unknown command '{}'", cmd).into()),
+    }
+}
+
+fn report_errors(map: &SeatMap) {
+    for (line, e) in map.errors.iter() {
+        eprintln!("Line {}: {}", line, e);
+    }
 }
 
 fn part_one(input: &str) -> u64 {
-    let rows = 128;
-    let cols = 8;
-    let mut max_seat_id = 0;
-
-    for pass in input.lines() {
-        let seat_id = find_seat_id(pass, rows, cols);
-        max_seat_id = if seat_id > max_seat_id {
-            seat_id
-        } else {
-            max_seat_id
-        };
+    SeatMap::new(Layout::STANDARD, input)
+        .max_seat_id()
+        .unwrap_or(0)
+}
+
+fn part_two(input: &str) -> Option<u64> {
+    SeatMap::new(Layout::STANDARD, input).your_seat()
+}
+
+/// Which seats on a plane are taken, by seat ID, along with any passes that
+/// couldn't be placed.
+#[derive(Debug, Clone)]
+struct SeatMap {
+    layout: Layout,
+    /// The input line of the pass for each taken seat.
+    taken: Vec<Option<usize>>,
+    errors: Vec<(usize, PassError)>,
+}
+
+impl SeatMap {
+    /// Builds the map from one pass per line. Malformed and duplicate passes
+    /// are skipped and recorded in `errors` with their line numbers.
+    fn new(layout: Layout, input: &str) -> Self {
+        let mut taken = vec![None; (layout.rows() * layout.cols()) as usize];
+        let mut errors = Vec::new();
+
+        for (i, line) in input.lines().enumerate() {
+            let line_number = i + 1;
+            let pass = match layout.parse(line) {
+                Ok(pass) => pass,
+                Err(e) => {
+                    errors.push((line_number, e));
+                    continue;
+                }
+            };
+
+            match taken[pass.id as usize] {
+                Some(first_line) => errors.push((
+                    line_number,
+                    PassError::Duplicate {
+                        id: pass.id,
+                        first_line,
+                    },
+                )),
+                None => taken[pass.id as usize] = Some(line_number),
+            }
+        }
+
+        SeatMap {
+            layout,
+            taken,
+            errors,
+        }
+    }
+
+    fn is_taken(&self, id: u64) -> bool {
+        matches!(self.taken.get(id as usize), Some(Some(_)))
+    }
+
+    fn max_seat_id(&self) -> Option<u64> {
+        self.taken
+            .iter()
+            .rposition(Option::is_some)
+            .map(|id| id as u64)
+    }
+
+    /// Every empty seat with both neighbouring seat IDs taken.
+    fn gaps(&self) -> Vec<u64> {
+        (1..self.taken.len() as u64)
+            .filter(|&id| !self.is_taken(id) && self.is_taken(id - 1) && self.is_taken(id + 1))
+            .collect()
     }
 
-    max_seat_id
+    /// The first gap at least a row away from both ends of the taken seats,
+    /// since the seats at the very front and back aren't on this plane.
+    fn your_seat(&self) -> Option<u64> {
+        let cols = self.layout.cols();
+        let end = self.max_seat_id()?.saturating_sub(cols);
+        self.gaps().into_iter().find(|&id| id >= cols && id < end)
+    }
+
+    /// How many seats are taken in each row.
+    fn row_occupancy(&self) -> Vec<u64> {
+        self.taken
+            .chunks(self.layout.cols() as usize)
+            .map(|row| row.iter().filter(|taken| taken.is_some()).count() as u64)
+            .collect()
+    }
+
+    /// One line per row: the row number, its seats (`#` taken, `.` empty, `X`
+    /// your seat), and how many are taken.
+    fn render(&self) -> String {
+        let yours = self.your_seat();
+        let width = (self.layout.rows() - 1).to_string().len();
+        let cols = self.layout.cols() as usize;
+
+        let mut out = String::new();
+        for (row, count) in self.row_occupancy().iter().enumerate() {
+            let cells: String = (0..cols)
+                .map(|col| {
+                    let id = (row * cols + col) as u64;
+                    match (self.is_taken(id), Some(id) == yours) {
+                        (true, _) => '#',
+                        (false, true) => 'X',
+                        (false, false) => '.',
+                    }
+                })
+                .collect();
+            out.push_str(&format!(
+                "{:>width$} {} {}\n",
+                row,
+                cells,
+                count,
+                width = width
+            ));
+        }
+
+        out
+    }
 }
 
-fn part_two(input: &str) -> u64 {
-    let rows = 128;
-    let cols = 8;
-    let mut max_seat_id = 0;
-    let mut taken_seats = HashSet::new();
-
-    for pass in input.lines() {
-        let seat_id = find_seat_id(pass, rows, cols);
-        taken_seats.insert(seat_id);
-        max_seat_id = if seat_id > max_seat_id {
-            seat_id
+/// How a plane's seats are encoded on its boarding passes: the row, most
+/// significant bit first, followed by the column, with one letter for each
+/// bit value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Layout {
+    row_bits: u32,
+    col_bits: u32,
+    /// Letters for a row bit of 0 and 1.
+    row_letters: (char, char),
+    /// Letters for a column bit of 0 and 1.
+    col_letters: (char, char),
+}
+
+impl Layout {
+    /// 128 rows and 8 columns, as in the puzzle.
+    const STANDARD: Layout = Layout {
+        row_bits: 7,
+        col_bits: 3,
+        row_letters: ('F', 'B'),
+        col_letters: ('L', 'R'),
+    };
+
+    fn rows(&self) -> u64 {
+        1 << self.row_bits
+    }
+
+    fn cols(&self) -> u64 {
+        1 << self.col_bits
+    }
+
+    fn pass_len(&self) -> usize {
+        (self.row_bits + self.col_bits) as usize
+    }
+
+    /// The letters for a 0 and a 1 at position `i` of a pass.
+    fn letters(&self, i: usize) -> (char, char) {
+        if i < self.row_bits as usize {
+            self.row_letters
         } else {
-            max_seat_id
-        };
+            self.col_letters
+        }
     }
 
-    for i in 8..(max_seat_id - 8) {
-        if taken_seats.get(&i).is_none()
-            && taken_seats.get(&(i - 1)).is_some()
-            && taken_seats.get(&(i + 1)).is_some()
-        {
-            return i;
+    /// Reads the pass as a binary number, which is `row * cols + col`.
+    fn parse(&self, pass: &str) -> Result<BoardingPass, PassError> {
+        let len = pass.chars().count();
+        if len != self.pass_len() {
+            return Err(PassError::WrongLength {
+                expected: self.pass_len(),
+                found: len,
+            });
         }
+
+        let mut id = 0;
+        for (i, c) in pass.chars().enumerate() {
+            let (zero, one) = self.letters(i);
+            let bit = match c {
+                c if c == zero => 0,
+                c if c == one => 1,
+                _ => {
+                    return Err(PassError::BadLetter {
+                        index: i,
+                        found: c,
+                        expected: (zero, one),
+                    })
+                }
+            };
+            id = id << 1 | bit;
+        }
+
+        Ok(BoardingPass {
+            seat: Seat {
+                row: id >> self.col_bits,
+                col: id & (self.cols() - 1),
+            },
+            id,
+        })
     }
 
-    panic!("Can't find your seat");
-}
+    /// The boarding pass for a seat.
+    fn encode(&self, seat: Seat) -> Result<String, PassError> {
+        if seat.row >= self.rows() {
+            return Err(PassError::RowOutOfRange(seat.row));
+        }
+        if seat.col >= self.cols() {
+            return Err(PassError::ColOutOfRange(seat.col));
+        }
+        self.encode_id(seat.row << self.col_bits | seat.col)
+    }
 
-fn find_seat_id(boarding_pass: &str, rows: u64, cols: u64) -> u64 {
-    let row_directions = boarding_pass.chars().take(7);
-    let mut low = 0;
-    let mut high = rows - 1;
-    for dir in row_directions {
-        let mid = (high - low) / 2 + low;
-        match dir {
-            'F' => {
-                high = mid;
-            }
-            'B' => {
-                low = mid + 1;
-            }
-            _ => panic!("Bad Input: must be 'F' or 'B'"),
+    fn encode_id(&self, id: u64) -> Result<String, PassError> {
+        if id >= self.rows() * self.cols() {
+            return Err(PassError::IdOutOfRange(id));
         }
+
+        let len = self.pass_len();
+        let pass = (0..len)
+            .map(|i| {
+                let (zero, one) = self.letters(i);
+                if id >> (len - 1 - i) & 1 == 0 {
+                    zero
+                } else {
+                    one
+                }
+            })
+            .collect();
+
+        Ok(pass)
     }
-    let row = (high - low) / 2 + low;
+}
 
-    let col_directions = boarding_pass.chars().skip(7);
-    let mut low = 0;
-    let mut high = cols - 1;
-    for dir in col_directions {
-        let mid = (high - low) / 2 + low;
-        match dir {
-            'L' => {
-                high = mid;
-            }
-            'R' => {
-                low = mid + 1;
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Seat {
+    row: u64,
+    col: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BoardingPass {
+    seat: Seat,
+    id: u64,
+}
+
+impl FromStr for BoardingPass {
+    type Err = PassError;
+
+    /// Parses a pass for the standard layout.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Layout::STANDARD.parse(s)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PassError {
+    WrongLength {
+        expected: usize,
+        found: usize,
+    },
+    BadLetter {
+        index: usize,
+        found: char,
+        expected: (char, char),
+    },
+    RowOutOfRange(u64),
+    ColOutOfRange(u64),
+    IdOutOfRange(u64),
+    Duplicate {
+        id: u64,
+        first_line: usize,
+    },
+}
+
+impl fmt::Display for PassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use PassError::*;
+
+        match self {
+            WrongLength { expected, found } => write!(
+                f,
+                "Boarding Pass Error: expected {} letters, found {}",
+                expected, found
+            ),
+            BadLetter {
+                index,
+                found,
+                expected: (zero, one),
+            } => write!(
+                f,
+                "Boarding Pass Error: expected '{}' or '{}' at position {}, found '{}'",
+                zero,
+                one,
+                index + 1,
+                found
+            ),
+            RowOutOfRange(row) => write!(f, "Boarding Pass Error: no row {} on the plane", row),
+            ColOutOfRange(col) => {
+                write!(f, "Boarding Pass Error: no column {} on the plane", col)
             }
-            _ => panic!("Bad Input: must be 'L' or 'R'"),
+            IdOutOfRange(id) => write!(f, "Boarding Pass Error: no seat ID {} on the plane", id),
+            Duplicate { id, first_line } => write!(
+                f,
+                "Boarding Pass Error: seat ID {} already taken by the pass on line {}",
+                id, first_line
+            ),
         }
     }
-    let col = (high - low) / 2 + low;
-
-    row * 8 + col
 }
 
+impl Error for PassError {}
+
 #[cfg(test)]
 mod test {
     use crate::*;
@@ -107,4 +407,182 @@ BBFFBBFRLL";
         let res = part_one(INPUT);
         assert_eq!(820, res);
     }
+
+    #[test]
+    fn decodes_seats() {
+        let layout = Layout::STANDARD;
+        assert_eq!(
+            Ok(BoardingPass {
+                seat: Seat { row: 70, col: 7 },
+                id: 567
+            }),
+            layout.parse("BFFFBBFRRR")
+        );
+        assert_eq!(Ok(14 * 8 + 7), layout.parse("FFFBBBFRRR").map(|p| p.id));
+        assert_eq!(Ok(102 * 8 + 4), layout.parse("BBFFBBFRLL").map(|p| p.id));
+        assert_eq!(Ok(357), "FBFBBFFRLR".parse().map(|p: BoardingPass| p.id));
+    }
+
+    #[test]
+    fn decodes_other_layouts() {
+        let layout = Layout {
+            row_bits: 5,
+            col_bits: 2,
+            row_letters: ('U', 'D'),
+            col_letters: ('A', 'Z'),
+        };
+        assert_eq!(Ok(19 * 4 + 2), layout.parse("DUUDDZA").map(|p| p.id));
+        assert!(layout.parse("BFFFBBFRRR").is_err());
+        assert!(layout.parse("DUUDDZ").is_err());
+    }
+
+    #[test]
+    fn encodes_seats() {
+        let layout = Layout::STANDARD;
+        assert_eq!(
+            Ok("BFFFBBFRRR".to_owned()),
+            layout.encode(Seat { row: 70, col: 7 })
+        );
+        assert_eq!(Ok("FBFBBFFRLR".to_owned()), layout.encode_id(357));
+        assert_eq!(
+            Err(PassError::RowOutOfRange(128)),
+            layout.encode(Seat { row: 128, col: 0 })
+        );
+        assert_eq!(
+            Err(PassError::ColOutOfRange(8)),
+            layout.encode(Seat { row: 0, col: 8 })
+        );
+        assert_eq!(Err(PassError::IdOutOfRange(1024)), layout.encode_id(1024));
+    }
+
+    #[test]
+    fn encode_decode_round_trips() {
+        let other = Layout {
+            row_bits: 5,
+            col_bits: 2,
+            row_letters: ('U', 'D'),
+            col_letters: ('A', 'Z'),
+        };
+
+        for layout in [Layout::STANDARD, other].iter() {
+            for row in 0..layout.rows() {
+                for col in 0..layout.cols() {
+                    let seat = Seat { row, col };
+                    let pass = layout.encode(seat).unwrap();
+                    let id = row * layout.cols() + col;
+
+                    assert_eq!(Ok(BoardingPass { seat, id }), layout.parse(&pass));
+                    assert_eq!(Ok(pass), layout.encode_id(id));
+                }
+            }
+        }
+    }
+
+    const SMALL_INPUT: &str = "\
+FFFFFFFLLL
+FFFFFFFLRL
+FFFFFFFLRR
+FFFFFFFRRL
+FFFFFFBLLR
+FFFFFFBLRR";
+
+    #[test]
+    fn seat_map_analytics() {
+        let map = SeatMap::new(Layout::STANDARD, SMALL_INPUT);
+
+        assert_eq!(vec![1, 10], map.gaps());
+        assert_eq!(None, map.your_seat());
+
+        let occupancy = map.row_occupancy();
+        assert_eq!(128, occupancy.len());
+        assert_eq!(&[4, 2, 0], &occupancy[..3]);
+
+        let render = map.render();
+        let lines: Vec<&str> = render.lines().collect();
+        assert_eq!(128, lines.len());
+        assert_eq!("  0 #.##..#. 4", lines[0]);
+        assert_eq!("  1 .#.#.... 2", lines[1]);
+        assert_eq!("127 ........ 0", lines[127]);
+    }
+
+    #[test]
+    fn your_seat_is_not_at_either_end() {
+        let passes: Vec<String> = (0..=30)
+            .filter(|&id| id != 12)
+            .map(|id| Layout::STANDARD.encode_id(id).unwrap())
+            .collect();
+        let map = SeatMap::new(Layout::STANDARD, &passes.join("\n"));
+
+        assert_eq!(Some(12), map.your_seat());
+        assert_eq!(Some(12), part_two(&passes.join("\n")));
+        assert_eq!("  1 ####X### 7", map.render().lines().nth(1).unwrap());
+    }
+
+    #[test]
+    fn rejects_malformed_passes() {
+        assert_eq!(
+            Err(PassError::WrongLength {
+                expected: 10,
+                found: 9
+            }),
+            "BFFFBBFRR".parse::<BoardingPass>()
+        );
+        assert_eq!(
+            Err(PassError::WrongLength {
+                expected: 10,
+                found: 11
+            }),
+            "BFFFBBFRRRR".parse::<BoardingPass>()
+        );
+        assert_eq!(
+            Err(PassError::BadLetter {
+                index: 7,
+                found: 'F',
+                expected: ('L', 'R')
+            }),
+            "BFFFBBFFRR".parse::<BoardingPass>()
+        );
+    }
+
+    #[test]
+    fn reports_bad_and_duplicate_passes() {
+        let map = SeatMap::new(Layout::STANDARD, "BFFFBBFRRR\nBFFFBBFXRR\n\nBFFFBBFRRR");
+
+        assert_eq!(Some(567), map.max_seat_id());
+        assert_eq!(
+            vec![
+                (
+                    2,
+                    PassError::BadLetter {
+                        index: 7,
+                        found: 'X',
+                        expected: ('L', 'R')
+                    }
+                ),
+                (
+                    3,
+                    PassError::WrongLength {
+                        expected: 10,
+                        found: 0
+                    }
+                ),
+                (
+                    4,
+                    PassError::Duplicate {
+                        id: 567,
+                        first_line: 1
+                    }
+                ),
+            ],
+            map.errors
+        );
+    }
+
+    #[test]
+    fn small_inputs_do_not_underflow() {
+        assert_eq!(0, part_one(""));
+        assert_eq!(None, part_two(""));
+        assert_eq!(None, part_two("FFFFFFFLLR"));
+        assert_eq!(None, part_two("FFFFFFFLLL\nFFFFFFFLLR"));
+    }
 }
@@ -1,60 +1,300 @@
 //! --- Day 6: Custom Customs ---
 //! https://adventofcode.com/2020/day/6
 
-use std::collections::HashSet;
+mod report;
+
+use common::records::{Options, Records};
+use report::Report;
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    error::Error,
+    fs, io,
+    iter::FromIterator,
+    process,
+    str::FromStr,
+};
 
 const INPUT: &str = include_str!("../../inputs/day06.txt");
 
+const USAGE: &str = "\
+Usage: day06 total AGGREGATION [FILE]
+       day06 report [--format csv|json] [FILE]
+
+AGGREGATION is one of:
+    union          questions anyone in the group answered
+    intersection   questions everyone in the group answered
+    symdiff        questions an odd number of people answered
+    exactly=K      questions exactly K people answered
+    at-least=P     questions at least P% of the group answered
+
+report lists how many members of each group answered each question, then
+how many people answered each question overall. FILE defaults to the bundled
+input. With no arguments, prints both parts for the bundled input.";
+
 fn main() {
-    println!("Part one: {}", part_one(INPUT));
-    println!("Part two: {}", part_two(INPUT));
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        println!("Part one: {}", part_one(INPUT));
+        println!("Part two: {}", part_two(INPUT));
+        return;
+    }
+
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
 }
 
-fn part_one(input: &str) -> u64 {
-    let mut total = 0;
-    let mut group_ans = HashSet::new();
-    for line in input.lines() {
-        if line == "" {
-            total += group_ans.len();
-            group_ans.clear();
-            continue;
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    match args[0].as_str() {
+        "total" => {
+            let mut positionals = Vec::new();
+            for arg in &args[1..] {
+                positionals.push(positional(arg, positionals.len(), 2)?);
+            }
+            let aggregation: Aggregation =
+                positionals.first().ok_or("Missing aggregation")?.parse()?;
+            let input = match positionals.get(1) {
+                Some(path) => fs::read_to_string(path)?,
+                None => INPUT.to_owned(),
+            };
+            println!("{}", total(&parse_groups(&input), aggregation));
+
+            Ok(())
         }
+        "report" => {
+            let mut format = report::Format::Csv;
+            let mut paths = Vec::new();
+            let mut iter = args[1..].iter();
+            while let Some(arg) = iter.next() {
+                match arg.as_str() {
+                    "--format" => {
+                        format = iter.next().ok_or("Missing value for --format")?.parse()?
+                    }
+                    _ => paths.push(positional(arg, paths.len(), 1)?),
+                }
+            }
 
-        line.chars().for_each(|c| {
-            group_ans.insert(c);
-        });
+            let input = match paths.first() {
+                Some(path) => fs::read_to_string(path)?,
+                None => INPUT.to_owned(),
+            };
+            Report::new(&parse_groups(&input)).write(format, io::stdout().lock())?;
+
+            Ok(())
+        }
+        cmd => Err(format!("Unknown command '{}'", cmd).into()),
     }
-    total += group_ans.len();
+}
 
-    total as u64
+/// An argument that isn't a known flag, if there's room for another one.
+fn positional(arg: &str, taken: usize, max: usize) -> Result<&str, Box<dyn Error>> {
+    if arg.starts_with("--") {
+        Err(format!("Unknown flag '{}'", arg).into())
+    } else if taken >= max {
+        Err(format!("Unexpected argument '{}'", arg).into())
+    } else {
+        Ok(arg)
+    }
+}
+
+fn part_one(input: &str) -> u64 {
+    total(&parse_groups(input), Aggregation::Union)
 }
 
 fn part_two(input: &str) -> u64 {
-    let mut total = 0;
-    let mut group_ans = HashSet::new();
-    let mut fresh = true;
-
-    for line in input.lines() {
-        if line == "" {
-            total += group_ans.len();
-            group_ans.clear();
-            fresh = true;
-            continue;
+    total(&parse_groups(input), Aggregation::Intersection)
+}
+
+fn total(groups: &[Group], aggregation: Aggregation) -> u64 {
+    groups
+        .iter()
+        .map(|group| group.answers(aggregation).len() as u64)
+        .sum()
+}
+
+fn parse_groups(input: &str) -> Vec<Group> {
+    Records::new(input, Options::default())
+        .map(|lines| Group {
+            people: lines.iter().map(|line| line.chars().collect()).collect(),
+        })
+        .collect()
+}
+
+/// Which questions each person in a group answered "yes" to.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Group {
+    people: Vec<Questions>,
+}
+
+impl Group {
+    fn answers(&self, aggregation: Aggregation) -> Questions {
+        match aggregation {
+            Aggregation::Union => self
+                .people
+                .iter()
+                .fold(Questions::default(), |acc, person| acc.union(person)),
+            Aggregation::Intersection => {
+                let mut people = self.people.iter();
+                let first = people.next().cloned().unwrap_or_default();
+                people.fold(first, |acc, person| acc.intersection(person))
+            }
+            _ => self
+                .counts()
+                .into_iter()
+                .filter(|&(_, count)| aggregation.selects(count, self.people.len()))
+                .map(|(question, _)| question)
+                .collect(),
+        }
+    }
+
+    /// How many people answered each question.
+    fn counts(&self) -> HashMap<char, usize> {
+        let mut counts = HashMap::new();
+        for question in self.people.iter().flat_map(Questions::iter) {
+            *counts.entry(question).or_insert(0) += 1;
         }
 
-        if fresh {
-            group_ans = line.chars().collect();
+        counts
+    }
+}
+
+/// A set of questions. Sets of `a` to `z` are stored as a bitset, with one
+/// bit per letter, and anything else falls back to a `HashSet`.
+///
+/// Always built through `collect`, so a set of `a` to `z` is never stored as
+/// `Chars`, which keeps the derived `PartialEq` correct.
+#[derive(Debug, Clone, PartialEq)]
+enum Questions {
+    Bits(u32),
+    Chars(HashSet<char>),
+}
+
+impl Default for Questions {
+    fn default() -> Self {
+        Questions::Bits(0)
+    }
+}
+
+impl Questions {
+    fn bit(c: char) -> Option<u32> {
+        if c.is_ascii_lowercase() {
+            Some(1 << (c as u32 - 'a' as u32))
         } else {
-            group_ans = group_ans
-                .intersection(&line.chars().collect::<HashSet<char>>())
-                .copied()
-                .collect();
+            None
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Questions::Bits(bits) => bits.count_ones() as usize,
+            Questions::Chars(chars) => chars.len(),
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = char> + '_> {
+        match self {
+            Questions::Bits(bits) => {
+                let bits = *bits;
+                Box::new(
+                    ('a'..='z')
+                        .enumerate()
+                        .filter(move |(i, _)| bits & 1 << i != 0)
+                        .map(|(_, c)| c),
+                )
+            }
+            Questions::Chars(chars) => Box::new(chars.iter().copied()),
+        }
+    }
+
+    fn union(&self, other: &Questions) -> Questions {
+        match (self, other) {
+            (Questions::Bits(a), Questions::Bits(b)) => Questions::Bits(a | b),
+            _ => self.iter().chain(other.iter()).collect(),
+        }
+    }
+
+    fn intersection(&self, other: &Questions) -> Questions {
+        match (self, other) {
+            (Questions::Bits(a), Questions::Bits(b)) => Questions::Bits(a & b),
+            _ => {
+                let other: HashSet<char> = other.iter().collect();
+                self.iter().filter(|c| other.contains(c)).collect()
+            }
+        }
+    }
+}
+
+impl FromIterator<char> for Questions {
+    fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> Self {
+        let mut bits = 0;
+        let mut iter = iter.into_iter();
+        while let Some(c) = iter.next() {
+            match Self::bit(c) {
+                Some(bit) => bits |= bit,
+                None => {
+                    let mut chars: HashSet<char> = Questions::Bits(bits).iter().collect();
+                    chars.insert(c);
+                    chars.extend(iter);
+                    return Questions::Chars(chars);
+                }
+            }
         }
-        fresh = false;
+
+        Questions::Bits(bits)
     }
-    total += group_ans.len();
+}
 
-    total as u64
+/// How to combine the answers of everyone in a group.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Aggregation {
+    Union,
+    Intersection,
+    SymmetricDifference,
+    Exactly(usize),
+    /// Answered by at least this percentage of the group.
+    AtLeastPercent(u32),
+}
+
+impl Aggregation {
+    /// Whether a question answered by `count` of `members` people is kept.
+    fn selects(self, count: usize, members: usize) -> bool {
+        use Aggregation::*;
+
+        match self {
+            Union => count > 0,
+            Intersection => count == members,
+            SymmetricDifference => count % 2 == 1,
+            Exactly(k) => count == k,
+            AtLeastPercent(p) => count > 0 && count * 100 >= p as usize * members,
+        }
+    }
+}
+
+impl FromStr for Aggregation {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            None => match s {
+                "union" => Ok(Aggregation::Union),
+                "intersection" => Ok(Aggregation::Intersection),
+                "symdiff" => Ok(Aggregation::SymmetricDifference),
+                _ => Err(format!("Unknown aggregation '{}'", s).into()),
+            },
+            Some(("exactly", k)) => Ok(Aggregation::Exactly(k.parse()?)),
+            Some(("at-least", p)) => {
+                let p = p.trim_end_matches('%').parse()?;
+                if p > 100 {
+                    return Err(format!("Percentage {} is over 100", p).into());
+                }
+                Ok(Aggregation::AtLeastPercent(p))
+            }
+            Some(_) => Err(format!("Unknown aggregation '{}'", s).into()),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -89,4 +329,92 @@ b";
         let res = part_two(INPUT);
         assert_eq!(6, res);
     }
+
+    #[test]
+    fn aggregations() {
+        let groups = parse_groups(INPUT);
+        let totals: Vec<u64> = [
+            "symdiff",
+            "exactly=1",
+            "exactly=2",
+            "at-least=50",
+            "at-least=51%",
+        ]
+        .iter()
+        .map(|s| total(&groups, s.parse().unwrap()))
+        .collect();
+
+        assert_eq!(vec![9, 9, 1, 8, 6], totals);
+    }
+
+    #[test]
+    fn group_answers() {
+        let group = &parse_groups("ab\nac\nad")[0];
+        let set = |s: &str| s.chars().collect::<Questions>();
+
+        assert_eq!(set("abcd"), group.answers(Aggregation::Union));
+        assert_eq!(set("a"), group.answers(Aggregation::Intersection));
+        assert_eq!(set("abcd"), group.answers(Aggregation::SymmetricDifference));
+        assert_eq!(set("bcd"), group.answers(Aggregation::Exactly(1)));
+        assert_eq!(set("a"), group.answers(Aggregation::AtLeastPercent(34)));
+    }
+
+    #[test]
+    fn rejects_unknown_arguments() {
+        let err = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            run(&args).unwrap_err().to_string()
+        };
+
+        assert_eq!(
+            "Unexpected argument 'extra'",
+            err(&["total", "union", "file", "extra"])
+        );
+        assert_eq!("Unknown flag '--fromat'", err(&["report", "--fromat"]));
+        assert_eq!("Unexpected argument 'b'", err(&["report", "a", "b"]));
+    }
+
+    #[test]
+    fn parses_aggregations() {
+        assert!("exactly=x".parse::<Aggregation>().is_err());
+        assert!("at-least=101".parse::<Aggregation>().is_err());
+        assert!("median".parse::<Aggregation>().is_err());
+    }
+
+    #[test]
+    fn questions_use_bits_for_lowercase() {
+        let abc: Questions = "cab".chars().collect();
+        assert_eq!(Questions::Bits(0b111), abc);
+        assert_eq!(3, abc.len());
+        assert_eq!(vec!['a', 'b', 'c'], abc.iter().collect::<Vec<_>>());
+
+        let mixed: Questions = "aB1".chars().collect();
+        assert_eq!(
+            Questions::Chars(['a', 'B', '1'].iter().copied().collect()),
+            mixed
+        );
+
+        assert_eq!(Questions::Bits(0b1), abc.intersection(&mixed));
+        assert_eq!("abcB1".chars().collect::<Questions>(), abc.union(&mixed));
+        assert_eq!(5, abc.union(&mixed).len());
+    }
+
+    #[test]
+    fn other_characters_fall_back() {
+        let groups = parse_groups("aé\nbé\n\nX\nXY");
+        assert_eq!(3 + 2, total(&groups, Aggregation::Union));
+        assert_eq!(1 + 1, total(&groups, Aggregation::Intersection));
+        assert_eq!(2 + 1, total(&groups, Aggregation::Exactly(1)));
+    }
+
+    #[test]
+    fn tolerates_crlf_and_extra_blank_lines() {
+        let input = INPUT
+            .replace('\n', " \r\n")
+            .replace(" \r\n \r\n", "\r\n\r\n\r\n");
+        let groups = parse_groups(&input);
+
+        assert_eq!(parse_groups(INPUT), groups);
+        assert_eq!(5, groups.len());
+    }
 }
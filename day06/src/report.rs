@@ -0,0 +1,171 @@
+//! Per-group and overall answer statistics.
+
+use crate::Group;
+use serde::Serialize;
+use std::{collections::BTreeMap, error::Error, io::Write, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("Unknown format '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Report {
+    groups: Vec<GroupStats>,
+    /// How many people answered each question, across all groups.
+    histogram: BTreeMap<char, usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct GroupStats {
+    members: usize,
+    /// How many members answered each question.
+    answers: BTreeMap<char, usize>,
+}
+
+impl Report {
+    pub fn new(groups: &[Group]) -> Self {
+        let groups: Vec<GroupStats> = groups
+            .iter()
+            .map(|group| GroupStats {
+                members: group.people.len(),
+                answers: group.counts().into_iter().collect(),
+            })
+            .collect();
+
+        let mut histogram = BTreeMap::new();
+        for (question, count) in groups.iter().flat_map(|g| g.answers.iter()) {
+            *histogram.entry(*question).or_insert(0) += count;
+        }
+
+        Report { groups, histogram }
+    }
+
+    pub fn write(&self, format: Format, out: impl Write) -> Result<(), Box<dyn Error>> {
+        match format {
+            Format::Csv => self.write_csv(out),
+            Format::Json => self.write_json(out),
+        }
+    }
+
+    /// One row per group and question, then one row per question for the
+    /// histogram, with `all` as its group.
+    fn write_csv(&self, out: impl Write) -> Result<(), Box<dyn Error>> {
+        let mut writer = csv::Writer::from_writer(out);
+        writer.write_record(["group", "members", "question", "count"])?;
+
+        for (i, group) in self.groups.iter().enumerate() {
+            let number = (i + 1).to_string();
+            let members = group.members.to_string();
+            for (question, count) in group.answers.iter() {
+                writer.write_record([
+                    number.as_str(),
+                    &members,
+                    &question.to_string(),
+                    &count.to_string(),
+                ])?;
+            }
+        }
+
+        let people = self.groups.iter().map(|g| g.members).sum::<usize>();
+        for (question, count) in self.histogram.iter() {
+            writer.write_record([
+                "all",
+                &people.to_string(),
+                &question.to_string(),
+                &count.to_string(),
+            ])?;
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    fn write_json(&self, mut out: impl Write) -> Result<(), Box<dyn Error>> {
+        serde_json::to_writer_pretty(&mut out, self)?;
+        writeln!(out)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse_groups;
+
+    const INPUT: &str = "\
+ab
+ac
+
+b";
+
+    fn write(format: Format) -> String {
+        let mut out = Vec::new();
+        Report::new(&parse_groups(INPUT))
+            .write(format, &mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn counts_answers() {
+        let report = Report::new(&parse_groups(INPUT));
+
+        assert_eq!(2, report.groups[0].members);
+        assert_eq!(
+            vec![(&'a', &2), (&'b', &1), (&'c', &1)],
+            report.groups[0].answers.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![(&'a', &2), (&'b', &2), (&'c', &1)],
+            report.histogram.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn writes_csv() {
+        assert_eq!(
+            "\
+group,members,question,count
+1,2,a,2
+1,2,b,1
+1,2,c,1
+2,1,b,1
+all,3,a,2
+all,3,b,2
+all,3,c,1
+",
+            write(Format::Csv)
+        );
+    }
+
+    #[test]
+    fn writes_json() {
+        let json: serde_json::Value = serde_json::from_str(&write(Format::Json)).unwrap();
+
+        assert_eq!(
+            serde_json::json!({
+                "groups": [
+                    { "members": 2, "answers": { "a": 2, "b": 1, "c": 1 } },
+                    { "members": 1, "answers": { "b": 1 } }
+                ],
+                "histogram": { "a": 2, "b": 2, "c": 1 }
+            }),
+            json
+        );
+    }
+}
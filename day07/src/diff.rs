@@ -0,0 +1,214 @@
+//! Comparing two revisions of a rule set.
+
+use crate::BagGraph;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// What changed between two rule sets, and how that changes the answers
+/// for some bags.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub edges: Vec<EdgeChange>,
+    pub queries: Vec<QueryChange>,
+}
+
+/// A bag held by another in either rule set, with how many of it are held
+/// in each. `None` if the rule set doesn't have the edge.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EdgeChange {
+    pub parent: String,
+    pub child: String,
+    pub old: Option<u64>,
+    pub new: Option<u64>,
+}
+
+/// A query whose answer for a bag differs, as displayed, errors included.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryChange {
+    pub query: &'static str,
+    pub color: String,
+    pub old: String,
+    pub new: String,
+}
+
+impl Diff {
+    /// Compares the bags and edges, and the ancestor count and contained
+    /// total of each of `colors`.
+    pub fn new<'a>(
+        old: &BagGraph,
+        new: &BagGraph,
+        colors: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        let old_bags: BTreeSet<&str> = old.bags().map(|id| old.color(id)).collect();
+        let new_bags: BTreeSet<&str> = new.bags().map(|id| new.color(id)).collect();
+        let old_edges = edges(old);
+        let new_edges = edges(new);
+
+        let keys: BTreeSet<_> = old_edges.keys().chain(new_edges.keys()).collect();
+        let edges = keys
+            .into_iter()
+            .filter(|key| old_edges.get(*key) != new_edges.get(*key))
+            .map(|key| EdgeChange {
+                parent: key.0.to_owned(),
+                child: key.1.to_owned(),
+                old: old_edges.get(key).copied(),
+                new: new_edges.get(key).copied(),
+            })
+            .collect();
+
+        let mut queries = Vec::new();
+        for color in colors {
+            let answers = [
+                (
+                    "ancestors",
+                    show(old.count_parents(color)),
+                    show(new.count_parents(color)),
+                ),
+                (
+                    "contained",
+                    show(old.count_contained(color)),
+                    show(new.count_contained(color)),
+                ),
+            ];
+            for (query, old, new) in answers.iter() {
+                if old != new {
+                    queries.push(QueryChange {
+                        query,
+                        color: color.to_owned(),
+                        old: old.clone(),
+                        new: new.clone(),
+                    });
+                }
+            }
+        }
+
+        Diff {
+            added: new_bags
+                .difference(&old_bags)
+                .map(|b| b.to_string())
+                .collect(),
+            removed: old_bags
+                .difference(&new_bags)
+                .map(|b| b.to_string())
+                .collect(),
+            edges,
+            queries,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Diff::default()
+    }
+}
+
+/// How many of each bag each bag holds, adding up a bag its rule lists more
+/// than once.
+fn edges(bags: &BagGraph) -> BTreeMap<(&str, &str), u64> {
+    let mut edges = BTreeMap::new();
+    for parent in bags.bags() {
+        for (count, child) in bags.held_by(parent) {
+            *edges
+                .entry((bags.color(parent), bags.color(child)))
+                .or_insert(0) += count;
+        }
+    }
+    edges
+}
+
+fn show(answer: Result<usize, impl fmt::Display>) -> String {
+    match answer {
+        Ok(n) => n.to_string(),
+        Err(e) => e.to_string(),
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for bag in &self.added {
+            writeln!(f, "+ {}", bag)?;
+        }
+        for bag in &self.removed {
+            writeln!(f, "- {}", bag)?;
+        }
+        for edge in &self.edges {
+            let sign = match (edge.old, edge.new) {
+                (None, _) => '+',
+                (_, None) => '-',
+                _ => '~',
+            };
+            write!(f, "{} {} -> {}: ", sign, edge.parent, edge.child)?;
+            match (edge.old, edge.new) {
+                (Some(old), Some(new)) => writeln!(f, "{} -> {}", old, new)?,
+                (Some(n), None) | (None, Some(n)) => writeln!(f, "{}", n)?,
+                (None, None) => writeln!(f)?,
+            }
+        }
+        for change in &self.queries {
+            writeln!(
+                f,
+                "{} {}: {} -> {}",
+                change.query, change.color, change.old, change.new
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const OLD: &str = "\
+light red bags contain 1 bright white bag, 2 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 shiny gold bags.
+shiny gold bags contain 3 dark olive bags.
+dark olive bags contain no other bags.";
+
+    const NEW: &str = "\
+light red bags contain 1 bright white bag, 4 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain no other bags.
+shiny gold bags contain 3 dark olive bags, 1 faded blue bag.
+dark olive bags contain no other bags.
+faded blue bags contain no other bags.";
+
+    #[test]
+    fn compares_rule_sets() {
+        let old = BagGraph::parse(OLD).unwrap();
+        let new = BagGraph::parse(NEW).unwrap();
+        let diff = Diff::new(&old, &new, vec!["shiny gold", "light red", "dark olive"]);
+
+        assert_eq!(
+            "\
++ faded blue
+~ light red -> muted yellow: 2 -> 4
+- muted yellow -> shiny gold: 2
++ shiny gold -> faded blue: 1
+ancestors shiny gold: 3 -> 2
+contained shiny gold: 3 -> 4
+contained light red: 23 -> 10
+ancestors dark olive: 4 -> 3
+",
+            diff.to_string()
+        );
+    }
+
+    #[test]
+    fn reports_query_errors() {
+        let old = BagGraph::parse(OLD).unwrap();
+        let new = BagGraph::parse("light red bags contain 1 light red bag.").unwrap();
+        let diff = Diff::new(&old, &new, vec!["light red"]);
+
+        assert_eq!(
+            "Query Error: bags contain themselves: light red -> light red",
+            diff.queries[0].new
+        );
+        assert!(Diff::new(&old, &old, vec!["light red"]).is_empty());
+    }
+}
@@ -0,0 +1,291 @@
+//! Writing the rule graph as DOT, GraphML, or JSON node-link data.
+
+use crate::{query::QueryError, BagGraph};
+use serde::Serialize;
+use std::{error::Error, io::Write, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Dot,
+    GraphMl,
+    /// The node-link layout read by networkx and d3.
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(Format::Dot),
+            "graphml" => Ok(Format::GraphMl),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("Unknown format '{}'", s)),
+        }
+    }
+}
+
+/// How a bag relates to the highlighted target. A bag both inside and
+/// around the target, through a cycle, is an ancestor.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Target,
+    Ancestor,
+    Descendant,
+    Other,
+}
+
+impl Role {
+    fn name(self) -> &'static str {
+        match self {
+            Role::Target => "target",
+            Role::Ancestor => "ancestor",
+            Role::Descendant => "descendant",
+            Role::Other => "other",
+        }
+    }
+
+    fn fill(self) -> Option<&'static str> {
+        match self {
+            Role::Target => Some("gold"),
+            Role::Ancestor => Some("lightblue"),
+            Role::Descendant => Some("palegreen"),
+            Role::Other => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Export {
+    directed: bool,
+    nodes: Vec<Node>,
+    links: Vec<Link>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct Node {
+    id: String,
+    role: Role,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct Link {
+    source: String,
+    target: String,
+    count: u64,
+}
+
+impl Export {
+    /// The whole graph, with `target`'s ancestors and descendants marked if
+    /// given.
+    pub fn new(bags: &BagGraph, target: Option<&str>) -> Result<Self, QueryError> {
+        let target = target.map(|name| bags.find(name)).transpose()?;
+        let ancestors = target.map(|t| bags.ancestors_of(t));
+        let descendants = target.map(|t| bags.descendants_of(t));
+
+        let nodes = bags
+            .bags()
+            .map(|id| {
+                let role = if Some(id) == target {
+                    Role::Target
+                } else if ancestors.as_ref().is_some_and(|a| a.contains(&id)) {
+                    Role::Ancestor
+                } else if descendants.as_ref().is_some_and(|d| d.contains(&id)) {
+                    Role::Descendant
+                } else {
+                    Role::Other
+                };
+
+                Node {
+                    id: bags.color(id).to_owned(),
+                    role,
+                }
+            })
+            .collect();
+
+        let links = bags
+            .bags()
+            .flat_map(|parent| {
+                bags.held_by(parent).map(move |(count, child)| Link {
+                    source: bags.color(parent).to_owned(),
+                    target: bags.color(child).to_owned(),
+                    count,
+                })
+            })
+            .collect();
+
+        Ok(Export {
+            directed: true,
+            nodes,
+            links,
+        })
+    }
+
+    pub fn write(&self, format: Format, out: impl Write) -> Result<(), Box<dyn Error>> {
+        match format {
+            Format::Dot => self.write_dot(out),
+            Format::GraphMl => self.write_graphml(out),
+            Format::Json => self.write_json(out),
+        }
+    }
+
+    fn write_dot(&self, mut out: impl Write) -> Result<(), Box<dyn Error>> {
+        fn quote(s: &str) -> String {
+            format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+        }
+
+        writeln!(out, "digraph bags {{")?;
+        for node in &self.nodes {
+            write!(out, "    {}", quote(&node.id))?;
+            if let Some(fill) = node.role.fill() {
+                write!(out, " [style=filled, fillcolor={}]", fill)?;
+            }
+            writeln!(out, ";")?;
+        }
+        for link in &self.links {
+            writeln!(
+                out,
+                "    {} -> {} [label=\"{}\"];",
+                quote(&link.source),
+                quote(&link.target),
+                link.count
+            )?;
+        }
+        writeln!(out, "}}")?;
+
+        Ok(())
+    }
+
+    fn write_graphml(&self, mut out: impl Write) -> Result<(), Box<dyn Error>> {
+        fn escape(s: &str) -> String {
+            s.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+        }
+
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        writeln!(
+            out,
+            r#"  <key id="role" for="node" attr.name="role" attr.type="string"/>"#
+        )?;
+        writeln!(
+            out,
+            r#"  <key id="count" for="edge" attr.name="count" attr.type="long"/>"#
+        )?;
+        writeln!(out, r#"  <graph id="bags" edgedefault="directed">"#)?;
+        for node in &self.nodes {
+            writeln!(
+                out,
+                r#"    <node id="{}"><data key="role">{}</data></node>"#,
+                escape(&node.id),
+                node.role.name()
+            )?;
+        }
+        for link in &self.links {
+            writeln!(
+                out,
+                r#"    <edge source="{}" target="{}"><data key="count">{}</data></edge>"#,
+                escape(&link.source),
+                escape(&link.target),
+                link.count
+            )?;
+        }
+        writeln!(out, "  </graph>")?;
+        writeln!(out, "</graphml>")?;
+
+        Ok(())
+    }
+
+    fn write_json(&self, mut out: impl Write) -> Result<(), Box<dyn Error>> {
+        serde_json::to_writer_pretty(&mut out, self)?;
+        writeln!(out)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = "\
+light red bags contain 1 bright white bag, 2 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 faded blue bags.
+shiny gold bags contain 3 dark olive bags.
+dark olive bags contain no other bags.
+faded blue bags contain no other bags.";
+
+    fn export(format: Format, target: Option<&str>) -> String {
+        let bags = BagGraph::parse(INPUT).unwrap();
+        let mut out = Vec::new();
+        Export::new(&bags, target)
+            .unwrap()
+            .write(format, &mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn writes_dot() {
+        assert_eq!(
+            r#"digraph bags {
+    "light red" [style=filled, fillcolor=lightblue];
+    "bright white" [style=filled, fillcolor=lightblue];
+    "muted yellow";
+    "shiny gold" [style=filled, fillcolor=gold];
+    "faded blue";
+    "dark olive" [style=filled, fillcolor=palegreen];
+    "light red" -> "bright white" [label="1"];
+    "light red" -> "muted yellow" [label="2"];
+    "bright white" -> "shiny gold" [label="1"];
+    "muted yellow" -> "faded blue" [label="2"];
+    "shiny gold" -> "dark olive" [label="3"];
+}
+"#,
+            export(Format::Dot, Some("shiny gold"))
+        );
+        assert!(!export(Format::Dot, None).contains("fillcolor"));
+    }
+
+    #[test]
+    fn writes_graphml() {
+        let graphml = export(Format::GraphMl, Some("muted yellow"));
+
+        assert!(
+            graphml.contains(r#"<node id="faded blue"><data key="role">descendant</data></node>"#)
+        );
+        assert!(graphml.contains(
+            r#"<edge source="light red" target="muted yellow"><data key="count">2</data></edge>"#
+        ));
+        assert_eq!(6, graphml.matches("<node ").count());
+        assert_eq!(5, graphml.matches("<edge ").count());
+    }
+
+    #[test]
+    fn writes_json() {
+        let json: serde_json::Value =
+            serde_json::from_str(&export(Format::Json, Some("shiny gold"))).unwrap();
+
+        assert_eq!(
+            serde_json::json!({"id": "light red", "role": "ancestor"}),
+            json["nodes"][0]
+        );
+        assert_eq!(
+            serde_json::json!({"source": "shiny gold", "target": "dark olive", "count": 3}),
+            json["links"][4]
+        );
+    }
+
+    #[test]
+    fn unknown_targets_are_errors() {
+        let bags = BagGraph::parse(INPUT).unwrap();
+        assert!(Export::new(&bags, Some("shiny golf")).is_err());
+    }
+}
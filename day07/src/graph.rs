@@ -0,0 +1,523 @@
+//! The bag containment graph, built once from rules and then queried.
+
+use crate::{
+    query::QueryError,
+    rules::{parse_rules, ParseRuleError, Rule},
+};
+use num_bigint::BigUint;
+use num_traits::{CheckedAdd, CheckedMul, FromPrimitive, One, Zero};
+use petgraph::{graph::NodeIndex, visit::EdgeRef, Directed, Direction, Graph};
+use std::{
+    collections::{HashMap, HashSet},
+    iter::Extend,
+};
+
+/// An interned bag color. Only meaningful for the graph it came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BagId(NodeIndex);
+
+impl BagId {
+    pub fn index(self) -> usize {
+        self.0.index()
+    }
+}
+
+/// Collects bags and what they hold, interning each color the first time
+/// it's seen.
+#[derive(Debug, Default)]
+pub struct BagGraphBuilder {
+    ids: HashMap<String, BagId>,
+    graph: Graph<String, u64, Directed>,
+    /// The bags a rule has been added for.
+    described: HashSet<BagId>,
+}
+
+impl BagGraphBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The ID for a color, adding the bag if it's new.
+    pub fn bag(&mut self, color: &str) -> BagId {
+        if let Some(id) = self.ids.get(color) {
+            return *id;
+        }
+        let id = BagId(self.graph.add_node(color.to_owned()));
+        self.ids.insert(color.to_owned(), id);
+        id
+    }
+
+    /// Records that each `parent` bag holds `count` `child` bags.
+    pub fn holds(&mut self, parent: BagId, count: u64, child: BagId) -> &mut Self {
+        self.graph.add_edge(parent.0, child.0, count);
+        self
+    }
+
+    /// Adds the rule's bag and everything it holds. Only the first rule for
+    /// a bag counts; any later one is skipped, repeated or not.
+    pub fn rule(&mut self, rule: &Rule) -> &mut Self {
+        let parent = self.bag(&rule.color);
+        if !self.described.insert(parent) {
+            return self;
+        }
+        for (count, color) in &rule.contents {
+            let child = self.bag(color);
+            self.holds(parent, *count, child);
+        }
+        self
+    }
+
+    pub fn build(self) -> BagGraph {
+        BagGraph {
+            ids: self.ids,
+            graph: self.graph,
+        }
+    }
+}
+
+/// Which bags must directly hold which others, and how many.
+#[derive(Debug, Clone)]
+pub struct BagGraph {
+    ids: HashMap<String, BagId>,
+    graph: Graph<String, u64, Directed>,
+}
+
+impl BagGraph {
+    pub fn parse(input: &str) -> Result<Self, ParseRuleError> {
+        Ok(Self::from_rules(&parse_rules(input)?))
+    }
+
+    pub fn from_rules(rules: &[Rule]) -> Self {
+        let mut builder = BagGraphBuilder::new();
+        for rule in rules {
+            builder.rule(rule);
+        }
+        builder.build()
+    }
+
+    /// The ID for a color, if there's such a bag.
+    pub fn id(&self, color: &str) -> Option<BagId> {
+        self.ids.get(color).copied()
+    }
+
+    pub fn color(&self, id: BagId) -> &str {
+        &self.graph[id.0]
+    }
+
+    /// Every bag, in the order they were first seen.
+    pub fn bags(&self) -> impl Iterator<Item = BagId> + '_ {
+        self.graph.node_indices().map(BagId)
+    }
+
+    /// How many of each bag a bag directly holds, in the order its rule
+    /// lists them.
+    pub fn held_by(&self, id: BagId) -> impl Iterator<Item = (u64, BagId)> + '_ {
+        self.edges(id, Direction::Outgoing)
+    }
+
+    /// The bags directly holding a bag, and how many of it each holds, in
+    /// the order their rules were added.
+    pub fn holders(&self, id: BagId) -> impl Iterator<Item = (u64, BagId)> + '_ {
+        self.edges(id, Direction::Incoming)
+    }
+
+    fn edges(&self, id: BagId, direction: Direction) -> impl Iterator<Item = (u64, BagId)> + '_ {
+        // petgraph lists a node's edges newest first.
+        let mut edges: Vec<_> = self
+            .graph
+            .edges_directed(id.0, direction)
+            .map(|e| {
+                let other = match direction {
+                    Direction::Outgoing => e.target(),
+                    Direction::Incoming => e.source(),
+                };
+                (*e.weight(), BagId(other))
+            })
+            .collect();
+        edges.reverse();
+        edges.into_iter()
+    }
+
+    /// Looks up a bag, suggesting a near miss if there's no such bag.
+    pub fn find(&self, name: &str) -> Result<BagId, QueryError> {
+        self.id(name)
+            .ok_or_else(|| QueryError::unknown(name, self.ids.keys().map(String::as_str)))
+    }
+
+    pub fn count_parents(&self, name: &str) -> Result<usize, QueryError> {
+        Ok(self.ancestors_of(self.find(name)?).len())
+    }
+
+    /// The bags that can eventually hold a bag, not including itself.
+    pub fn ancestors_of(&self, id: BagId) -> HashSet<BagId> {
+        self.reachable(id, Direction::Incoming)
+    }
+
+    /// The bags a bag eventually holds, not including itself.
+    pub fn descendants_of(&self, id: BagId) -> HashSet<BagId> {
+        self.reachable(id, Direction::Outgoing)
+    }
+
+    fn reachable(&self, id: BagId, direction: Direction) -> HashSet<BagId> {
+        let mut found: HashSet<NodeIndex> = HashSet::new();
+        let mut todo = HashSet::new();
+        todo.insert(id.0);
+
+        while !todo.is_empty() {
+            found.extend(todo.iter());
+            todo = todo
+                .iter()
+                .flat_map(|n| self.graph.neighbors_directed(*n, direction))
+                .filter(|n| !found.contains(n))
+                .collect();
+        }
+        found.remove(&id.0);
+        found.into_iter().map(BagId).collect()
+    }
+
+    /// A chain of bags that eventually contain themselves, starting and
+    /// ending with the same bag, if the rules have any.
+    pub fn find_cycle(&self) -> Option<Vec<BagId>> {
+        self.cycle_among(self.graph.node_indices())
+    }
+
+    /// A chain of bags that eventually contain themselves, starting and
+    /// ending with the same bag, that a bag eventually holds, if any.
+    pub fn cycle_from(&self, id: BagId) -> Option<Vec<BagId>> {
+        self.cycle_among(std::iter::once(id.0))
+    }
+
+    /// An error for a cycle below a bag, if there is one.
+    pub(crate) fn check_acyclic(&self, id: BagId) -> Result<(), QueryError> {
+        match self.cycle_from(id) {
+            Some(cycle) => Err(QueryError::Cycle(self.colors(&cycle))),
+            None => Ok(()),
+        }
+    }
+
+    fn colors(&self, ids: &[BagId]) -> Vec<String> {
+        ids.iter().map(|id| self.color(*id).to_owned()).collect()
+    }
+
+    /// Depth first search for a cycle reachable from any of `starts`.
+    fn cycle_among(&self, starts: impl Iterator<Item = NodeIndex>) -> Option<Vec<BagId>> {
+        // `true` while a node is on the stack, `false` once it's finished.
+        let mut on_stack: HashMap<NodeIndex, bool> = HashMap::new();
+
+        for start in starts {
+            if on_stack.contains_key(&start) {
+                continue;
+            }
+            on_stack.insert(start, true);
+            let mut stack = vec![(
+                start,
+                self.graph.neighbors_directed(start, Direction::Outgoing),
+            )];
+
+            while let Some((node, children)) = stack.last_mut() {
+                match children.next() {
+                    Some(child) => match on_stack.get(&child) {
+                        Some(true) => {
+                            let from = stack.iter().position(|(n, _)| *n == child).unwrap_or(0);
+                            let mut cycle: Vec<BagId> =
+                                stack[from..].iter().map(|(n, _)| BagId(*n)).collect();
+                            cycle.push(BagId(child));
+                            return Some(cycle);
+                        }
+                        Some(false) => (),
+                        None => {
+                            on_stack.insert(child, true);
+                            stack.push((
+                                child,
+                                self.graph.neighbors_directed(child, Direction::Outgoing),
+                            ));
+                        }
+                    },
+                    None => {
+                        on_stack.insert(*node, false);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// How many bags a bag must hold, or an error if that doesn't fit in a
+    /// `usize`.
+    pub fn count_contained(&self, name: &str) -> Result<usize, QueryError> {
+        self.contained_in(self.find(name)?)
+    }
+
+    /// How many bags a bag must hold, however many that is.
+    pub fn count_contained_exact(&self, name: &str) -> Result<BigUint, QueryError> {
+        self.contained_in_exact(self.find(name)?)
+    }
+
+    /// As `count_contained`, by bag ID.
+    pub fn contained_in(&self, id: BagId) -> Result<usize, QueryError> {
+        self.total_contained(id)
+    }
+
+    /// As `count_contained_exact`, by bag ID.
+    pub fn contained_in_exact(&self, id: BagId) -> Result<BigUint, QueryError> {
+        self.total_contained(id)
+    }
+
+    fn total_contained<T>(&self, id: BagId) -> Result<T, QueryError>
+    where
+        T: Clone + Zero + One + CheckedAdd + CheckedMul + FromPrimitive,
+    {
+        self.check_acyclic(id)?;
+        let overflow = || QueryError::Overflow(self.color(id).to_owned());
+
+        // Each bag's total, not including itself, once all its children have one.
+        let mut totals: HashMap<BagId, T> = HashMap::new();
+        let mut stack = vec![(id, false)];
+
+        while let Some((bag, expanded)) = stack.pop() {
+            if totals.contains_key(&bag) {
+                continue;
+            }
+            if expanded {
+                let mut total = T::zero();
+                for (count, child) in self.held_by(bag) {
+                    let count = T::from_u64(count).ok_or_else(overflow)?;
+                    let bags = totals[&child]
+                        .checked_add(&T::one())
+                        .and_then(|bags| bags.checked_mul(&count))
+                        .and_then(|bags| total.checked_add(&bags));
+                    total = bags.ok_or_else(overflow)?;
+                }
+                totals.insert(bag, total);
+            } else {
+                stack.push((bag, true));
+                stack.extend(
+                    self.held_by(bag)
+                        .map(|(_, child)| child)
+                        .filter(|child| !totals.contains_key(child))
+                        .map(|child| (child, false)),
+                );
+            }
+        }
+
+        Ok(totals.remove(&id).unwrap_or_else(T::zero))
+    }
+
+    /// The bags directly and indirectly inside a bag, depth first, as the
+    /// level below the bag, how many are held by the bag above, and color.
+    /// Stops after `depth` levels, if given, otherwise fails if the bag
+    /// eventually contains itself.
+    pub fn contents(
+        &self,
+        name: &str,
+        depth: Option<usize>,
+    ) -> Result<Vec<(usize, u64, String)>, QueryError> {
+        let contents = self.contents_of(self.find(name)?, depth)?;
+
+        Ok(contents
+            .into_iter()
+            .map(|(level, count, id)| (level, count, self.color(id).to_owned()))
+            .collect())
+    }
+
+    /// As `contents`, with bag IDs rather than colors.
+    pub fn contents_of(
+        &self,
+        id: BagId,
+        depth: Option<usize>,
+    ) -> Result<Vec<(usize, u64, BagId)>, QueryError> {
+        let children = |bag| {
+            let mut children: Vec<_> = self.held_by(bag).collect();
+            children.sort_by(|a, b| self.color(b.1).cmp(self.color(a.1)));
+            children
+        };
+
+        if depth.is_none() {
+            self.check_acyclic(id)?;
+        }
+        let mut contents = Vec::new();
+        let mut todo: Vec<_> = children(id)
+            .into_iter()
+            .map(|(count, child)| (0, count, child))
+            .collect();
+
+        while let Some((level, count, bag)) = todo.pop() {
+            if depth.is_some_and(|depth| level >= depth) {
+                continue;
+            }
+            contents.push((level, count, bag));
+            todo.extend(
+                children(bag)
+                    .into_iter()
+                    .map(|(count, child)| (level + 1, count, child)),
+            );
+        }
+
+        Ok(contents)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures::{INPUT_ONE, INPUT_TWO};
+
+    #[test]
+    fn interns_colors() {
+        let mut builder = BagGraphBuilder::new();
+        let gold = builder.bag("shiny gold");
+        let olive = builder.bag("dark olive");
+        builder.holds(gold, 3, olive);
+
+        assert_eq!(gold, builder.bag("shiny gold"));
+
+        let bags = builder.build();
+        assert_eq!(Some(gold), bags.id("shiny gold"));
+        assert_eq!(None, bags.id("shiny golf"));
+        assert_eq!("dark olive", bags.color(olive));
+        assert_eq!(vec![gold, olive], bags.bags().collect::<Vec<_>>());
+        assert_eq!(vec![(3, olive)], bags.held_by(gold).collect::<Vec<_>>());
+        assert_eq!(vec![(3, gold)], bags.holders(olive).collect::<Vec<_>>());
+        assert_eq!(Ok(3), bags.count_contained("shiny gold"));
+    }
+
+    #[test]
+    fn keeps_the_first_rule() {
+        let rule = "a x bags contain 3 b y bags, 1 c z bag.\n";
+        let bags = BagGraph::parse(&rule.repeat(2)).unwrap();
+        assert_eq!(Ok(4), bags.count_contained("a x"));
+
+        let bags = BagGraph::parse(&format!("{}a x bags contain 5 b y bags.", rule)).unwrap();
+        assert_eq!(Ok(4), bags.count_contained("a x"));
+
+        let held: Vec<_> = bags
+            .held_by(bags.id("a x").unwrap())
+            .map(|(count, id)| (count, bags.color(id)))
+            .collect();
+        assert_eq!(vec![(3, "b y"), (1, "c z")], held);
+    }
+
+    #[test]
+    fn counts_deep_chains() {
+        let depth = 100_000;
+        let rules: String = (0..depth)
+            .map(|i| format!("bag {} bags contain 1 bag {} bag.\n", i, i + 1))
+            .chain(std::iter::once(format!(
+                "bag {} bags contain no other bags.",
+                depth
+            )))
+            .collect();
+        let bags = BagGraph::parse(&rules).unwrap();
+
+        assert_eq!(Ok(depth), bags.count_contained("bag 0"));
+        assert_eq!(Ok(1), bags.count_contained(&format!("bag {}", depth - 1)));
+    }
+
+    #[test]
+    fn overflow_is_an_error() {
+        let depth = 100;
+        let rules: String = (0..depth)
+            .map(|i| format!("bag {} bags contain 2 bag {} bags.\n", i, i + 1))
+            .collect();
+        let bags = BagGraph::parse(&rules).unwrap();
+
+        assert_eq!(
+            Err(QueryError::Overflow("bag 0".into())),
+            bags.count_contained("bag 0")
+        );
+        assert_eq!(Ok(126), bags.count_contained(&format!("bag {}", depth - 6)));
+
+        let exact = (BigUint::one() << (depth + 1)) - 2u32;
+        assert_eq!(Ok(exact), bags.count_contained_exact("bag 0"));
+        assert_eq!(
+            Ok(BigUint::from(126u32)),
+            BagGraph::parse(INPUT_TWO)
+                .unwrap()
+                .count_contained_exact("shiny gold")
+        );
+    }
+
+    #[test]
+    fn unknown_bags_are_errors() {
+        let bags = BagGraph::parse(INPUT_ONE).unwrap();
+        let err = QueryError::UnknownBag {
+            color: "shiny golf".into(),
+            suggestion: Some("shiny gold".into()),
+        };
+
+        assert_eq!(Err(err.clone()), bags.count_parents("shiny golf"));
+        assert_eq!(Err(err.clone()), bags.count_contained("shiny golf"));
+        assert_eq!(Err(err), bags.contents("shiny golf", None));
+    }
+
+    #[test]
+    fn lists_contents() {
+        let bags = BagGraph::parse(INPUT_ONE).unwrap();
+        let contents =
+            |depth| -> Vec<(usize, u64, String)> { bags.contents("light red", depth).unwrap() };
+        let row = |level, count, name: &str| (level, count, name.to_owned());
+
+        assert_eq!(
+            vec![
+                row(0, 1, "bright white"),
+                row(1, 1, "shiny gold"),
+                row(0, 2, "muted yellow"),
+                row(1, 9, "faded blue"),
+                row(1, 2, "shiny gold"),
+            ],
+            contents(Some(2))
+        );
+        assert_eq!(
+            vec![row(0, 1, "bright white"), row(0, 2, "muted yellow")],
+            contents(Some(1))
+        );
+        assert!(contents(Some(0)).is_empty());
+        assert_eq!(17, contents(None).len());
+    }
+
+    const CYCLIC: &str = "\
+light red bags contain 1 bright white bag, 2 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 faded blue bags.
+shiny gold bags contain 3 dark olive bags.
+dark olive bags contain 1 bright white bag.
+faded blue bags contain no other bags.";
+
+    #[test]
+    fn finds_cycles() {
+        let cycle = vec![
+            "bright white".to_owned(),
+            "shiny gold".to_owned(),
+            "dark olive".to_owned(),
+            "bright white".to_owned(),
+        ];
+        let bags = BagGraph::parse(CYCLIC).unwrap();
+        let found = bags.colors(&bags.find_cycle().unwrap());
+
+        assert_eq!(4, found.len());
+        assert_eq!(found.first(), found.last());
+        assert!(cycle[..3].contains(&found[0]));
+
+        assert_eq!(
+            Err(QueryError::Cycle(cycle)),
+            bags.count_contained("light red")
+        );
+        assert!(bags.contents("light red", None).is_err());
+        assert_eq!(4, bags.contents("light red", Some(2)).unwrap().len());
+        assert_eq!(Ok(2), bags.count_contained("muted yellow"));
+        assert_eq!(Ok(3), bags.count_parents("bright white"));
+
+        assert_eq!(None, BagGraph::parse(INPUT_ONE).unwrap().find_cycle());
+    }
+
+    #[test]
+    fn finds_self_containing_bags() {
+        let bags = BagGraph::parse("shiny gold bags contain 2 shiny gold bags.").unwrap();
+        let gold = bags.id("shiny gold").unwrap();
+
+        assert_eq!(Some(vec![gold, gold]), bags.find_cycle());
+        assert_eq!(Some(vec![gold, gold]), bags.cycle_from(gold));
+    }
+}
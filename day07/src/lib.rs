@@ -0,0 +1,16 @@
+//! --- Day 7: Handy Haversacks ---
+//! https://adventofcode.com/2020/day/7
+//!
+//! Bag rules, the containment graph built from them, and queries over it.
+
+pub mod diff;
+pub mod export;
+#[cfg(test)]
+mod fixtures;
+mod graph;
+pub mod lint;
+pub mod paths;
+pub mod query;
+pub mod rules;
+
+pub use graph::{BagGraph, BagGraphBuilder, BagId};
@@ -0,0 +1,205 @@
+//! Checking rule sets for mistakes that still parse.
+
+use crate::{query::QueryError, rules::Rule, BagGraph};
+use std::{collections::HashMap, fmt};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    /// The 1-based line of the rule the warning is about.
+    pub line: usize,
+    pub kind: WarningKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WarningKind {
+    /// A bag held by this rule that no rule describes.
+    Undefined(String),
+    /// A second rule for a bag, the same as the first.
+    Repeated { color: String, first_line: usize },
+    /// A second rule for a bag, holding something else than the first.
+    Conflicting { color: String, first_line: usize },
+    /// A bag held zero times, as parent and child.
+    ZeroCount(String, String),
+    /// A bag no other bag holds that holds nothing itself.
+    Orphan(String),
+    /// A bag that can neither hold the target nor be inside it.
+    Unreachable { color: String, target: String },
+}
+
+/// Every warning for `rules`, in line order. Bags unconnected to `target`
+/// are only flagged if it's given.
+pub fn lint(rules: &[Rule], target: Option<&str>) -> Result<Vec<Warning>, QueryError> {
+    let mut warnings = Vec::new();
+    let mut defined: HashMap<&str, &Rule> = HashMap::new();
+
+    for rule in rules {
+        match defined.get(rule.color.as_str()) {
+            Some(first) => {
+                let color = rule.color.clone();
+                let first_line = first.line;
+                let kind = if same_contents(first, rule) {
+                    WarningKind::Repeated { color, first_line }
+                } else {
+                    WarningKind::Conflicting { color, first_line }
+                };
+                warnings.push(Warning {
+                    line: rule.line,
+                    kind,
+                });
+            }
+            None => {
+                defined.insert(&rule.color, rule);
+            }
+        }
+    }
+
+    for rule in rules {
+        for (count, color) in &rule.contents {
+            if !defined.contains_key(color.as_str()) {
+                warnings.push(Warning {
+                    line: rule.line,
+                    kind: WarningKind::Undefined(color.clone()),
+                });
+            }
+            if *count == 0 {
+                warnings.push(Warning {
+                    line: rule.line,
+                    kind: WarningKind::ZeroCount(rule.color.clone(), color.clone()),
+                });
+            }
+        }
+    }
+
+    let bags = BagGraph::from_rules(rules);
+    let target = match target {
+        Some(target) => {
+            let id = bags.find(target)?;
+            let mut connected = bags.ancestors_of(id);
+            connected.extend(bags.descendants_of(id));
+            connected.insert(id);
+            Some((id, connected))
+        }
+        None => None,
+    };
+
+    for rule in defined.values() {
+        let id = match bags.id(&rule.color) {
+            Some(id) => id,
+            None => continue,
+        };
+        let isolated = bags
+            .held_by(id)
+            .chain(bags.holders(id))
+            .all(|(_, other)| other == id);
+        let kind = match &target {
+            _ if isolated => WarningKind::Orphan(rule.color.clone()),
+            Some((target, connected)) if !connected.contains(&id) => WarningKind::Unreachable {
+                color: rule.color.clone(),
+                target: bags.color(*target).to_owned(),
+            },
+            _ => continue,
+        };
+        warnings.push(Warning {
+            line: rule.line,
+            kind,
+        });
+    }
+
+    warnings.sort_by_key(|w| w.line);
+    Ok(warnings)
+}
+
+fn same_contents(a: &Rule, b: &Rule) -> bool {
+    let mut a = a.contents.clone();
+    let mut b = b.contents.clone();
+    a.sort();
+    b.sort();
+    a == b
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use WarningKind::*;
+
+        write!(f, "Warning on line {}: ", self.line)?;
+        match &self.kind {
+            Undefined(color) => write!(f, "no rule for '{}' bags", color),
+            Repeated { color, first_line } => write!(
+                f,
+                "'{}' bags were already described on line {}",
+                color, first_line
+            ),
+            Conflicting { color, first_line } => write!(
+                f,
+                "'{}' bags were already described differently on line {}",
+                color, first_line
+            ),
+            ZeroCount(parent, child) => {
+                write!(f, "'{}' bags hold 0 '{}' bags", parent, child)
+            }
+            Orphan(color) => write!(f, "'{}' bags hold nothing and are never held", color),
+            Unreachable { color, target } => write!(
+                f,
+                "'{}' bags can't hold or be inside '{}' bags",
+                color, target
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::parse_rules;
+
+    const INPUT: &str = "\
+light red bags contain 1 bright white bag, 0 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 faded blue bags.
+shiny gold bags contain 3 dark olive bags.
+dark olive bags contain no other bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 3 faded blue bags.
+dotted black bags contain no other bags.";
+
+    fn lint_input(target: Option<&str>) -> Vec<String> {
+        lint(&parse_rules(INPUT).unwrap(), target)
+            .unwrap()
+            .iter()
+            .map(Warning::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn flags_mistakes() {
+        assert_eq!(
+            vec![
+                "Warning on line 1: 'light red' bags hold 0 'muted yellow' bags",
+                "Warning on line 3: no rule for 'faded blue' bags",
+                "Warning on line 6: 'bright white' bags were already described on line 2",
+                "Warning on line 7: 'muted yellow' bags were already described differently on line 3",
+                "Warning on line 7: no rule for 'faded blue' bags",
+                "Warning on line 8: 'dotted black' bags hold nothing and are never held",
+            ],
+            lint_input(None)
+        );
+    }
+
+    #[test]
+    fn flags_bags_unconnected_to_the_target() {
+        let warnings = lint_input(Some("shiny gold"));
+
+        assert_eq!(7, warnings.len());
+        assert_eq!(
+            "Warning on line 3: 'muted yellow' bags can't hold or be inside 'shiny gold' bags",
+            warnings[2]
+        );
+        assert!(lint(
+            &parse_rules("a b bags contain 1 c d bag.\nc d bags contain no other bags.").unwrap(),
+            None
+        )
+        .unwrap()
+        .is_empty());
+        assert!(lint(&parse_rules(INPUT).unwrap(), Some("shiny golf")).is_err());
+    }
+}
@@ -1,179 +1,249 @@
 //! --- Day 7: Handy Haversacks ---
 //! https://adventofcode.com/2020/day/7
 
-use petgraph::{dot::Dot, graph::NodeIndex, visit::EdgeRef, Directed, Direction, Graph};
-use std::{
-    collections::{HashMap, HashSet},
-    iter::Extend,
+use day07::{
+    diff::Diff, export::Export, lint::lint, paths::heaviest, rules::parse_rules, BagGraph,
 };
+use std::{env, error::Error, fs, io, process};
+
+#[cfg(test)]
+mod fixtures;
 
 const INPUT: &str = include_str!("../../inputs/day07.txt");
 
+const USAGE: &str = "\
+Usage: day07 [--input FILE] ancestors COLOR [--list]
+       day07 [--input FILE] contents COLOR [--depth N] [--exact]
+       day07 [--input FILE] paths OUTER COLOR
+       day07 [--input FILE] cycles
+       day07 [--input FILE] lint [COLOR]
+       day07 diff OLD NEW [COLOR]
+       day07 [--input FILE] export dot|graphml|json [COLOR]
+
+ancestors counts the bags that can eventually hold a COLOR bag, and lists
+them with --list.
+contents lists what a COLOR bag must hold, down to N levels if given.
+Without --depth, the total is an error if it overflows. --exact prints just
+the exact total, however large, without listing anything.
+paths lists each chain of bags from an OUTER bag down to a COLOR bag, and
+how many COLOR bags it accounts for.
+cycles shows a chain of bags that eventually contain themselves, if any.
+export writes the rule graph, highlighting COLOR and the bags around and
+inside it if given.
+lint warns about rules that parse but look wrong, and bags that can't hold
+or be inside a COLOR bag if given.
+diff compares two rule files, and the answers for COLOR, or every bag in
+both, between them.
+FILE defaults to the bundled input. With no command, prints both parts.";
+
 fn main() {
-    println!("Part one: {}", part_one(INPUT));
-    println!("Part two: {}", part_two(INPUT));
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
 }
 
-fn part_one(input: &str) -> usize {
-    let target = "shiny gold";
-    let tree = build_tree(input);
-
-    tree.count_parents(target)
-}
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut input = None;
+    let mut depth = None;
+    let mut exact = false;
+    let mut list = false;
+    let mut flags = Vec::new();
+    let mut positional = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg.starts_with("--") {
+            flags.push(arg.as_str());
+        }
+        match arg.as_str() {
+            "--input" => input = Some(iter.next().ok_or("Missing value for --input")?),
+            "--depth" => depth = Some(iter.next().ok_or("Missing value for --depth")?.parse()?),
+            "--exact" => exact = true,
+            "--list" => list = true,
+            _ => positional.push(arg.as_str()),
+        }
+    }
 
-fn part_two(input: &str) -> usize {
-    let target = "shiny gold";
-    let tree = build_tree(input);
+    check_flags(positional.first().copied(), &flags)?;
 
-    tree.count_contained(target)
-}
+    let input = match input {
+        Some(path) => fs::read_to_string(path)?,
+        None => INPUT.to_owned(),
+    };
 
-fn build_tree(input: &str) -> Tree {
-    let mut tree = Tree::default();
-
-    for line in input.lines() {
-        let mut iter = line.split(" bags contain ");
-        let parent = iter.next().unwrap().trim();
-
-        for bag in iter.next().unwrap().trim_end_matches('.').split(", ") {
-            let bag = bag
-                .trim_end_matches(" bag")
-                .trim_end_matches(" bags")
-                .trim();
-            let i = bag.find(' ').unwrap();
-            match bag {
-                "no other" => {
-                    tree.add_node(&parent);
+    match positional.as_slice() {
+        [] => {
+            println!("Part one: {}", part_one(&input)?);
+            println!("Part two: {}", part_two(&input)?);
+        }
+        ["ancestors", color] if list => {
+            for ancestor in BagGraph::parse(&input)?.ancestors(color)? {
+                println!("{}", ancestor);
+            }
+        }
+        ["ancestors", color] => {
+            let count = BagGraph::parse(&input)?.count_parents(color)?;
+            println!(
+                "{} bag colors can eventually contain a {} bag",
+                count, color
+            );
+        }
+        ["contents", color] if exact => {
+            let bags = BagGraph::parse(&input)?;
+            println!("{} bags in total", bags.count_contained_exact(color)?);
+        }
+        ["contents", color] => {
+            let bags = BagGraph::parse(&input)?;
+            for (level, count, name) in bags.contents(color, depth)? {
+                println!("{}{} {}", "  ".repeat(level), count, name);
+            }
+            if depth.is_none() {
+                println!("{} bags in total", bags.count_contained(color)?);
+            }
+        }
+        ["paths", outer, color] => {
+            let bags = BagGraph::parse(&input)?;
+            let paths = bags.paths(outer, color)?;
+            for path in &paths {
+                println!("{}", path);
+            }
+            if let Some(path) = heaviest(&paths) {
+                println!("Most bags: {}", path);
+            }
+        }
+        ["cycles"] => {
+            let bags = BagGraph::parse(&input)?;
+            match bags.find_cycle() {
+                Some(cycle) => {
+                    let colors: Vec<&str> = cycle.iter().map(|id| bags.color(*id)).collect();
+                    println!("{}", colors.join(" -> "));
                 }
-                b => {
-                    let (count, name) = b.split_at(i);
-                    let count = count.trim().parse::<u16>().unwrap();
-                    let name = name.trim();
-                    tree.add_edge(parent, name, count);
+                None => println!("No bags contain themselves"),
+            }
+        }
+        ["lint", target @ ..] if target.len() <= 1 => {
+            let warnings = lint(&parse_rules(&input)?, target.first().copied())?;
+            for warning in &warnings {
+                println!("{}", warning);
+            }
+            if warnings.is_empty() {
+                println!("No warnings");
+            }
+        }
+        ["diff", old, new, color @ ..] if color.len() <= 1 => {
+            let old = BagGraph::parse(&fs::read_to_string(old)?)?;
+            let new = BagGraph::parse(&fs::read_to_string(new)?)?;
+            let colors: Vec<&str> = match color {
+                [color] => vec![*color],
+                _ => {
+                    let mut colors: Vec<&str> = old
+                        .bags()
+                        .map(|id| old.color(id))
+                        .filter(|c| new.id(c).is_some())
+                        .collect();
+                    colors.sort_unstable();
+                    colors
                 }
+            };
+            let diff = Diff::new(&old, &new, colors);
+            if diff.is_empty() {
+                println!("No differences");
+            } else {
+                print!("{}", diff);
             }
         }
-    }
-
-    tree
-}
-
-#[derive(Debug, Default)]
-struct Tree {
-    nodes: HashMap<String, NodeIndex>,
-    graph: Graph<String, u16, Directed>,
-}
-
-impl Tree {
-    pub fn add_node(&mut self, name: &str) {
-        if !self.nodes.contains_key(name) {
-            let node = self.graph.add_node(name.into());
-            self.nodes.insert(name.into(), node);
+        ["export", format, target @ ..] if target.len() <= 1 => {
+            let bags = BagGraph::parse(&input)?;
+            let export = Export::new(&bags, target.first().copied())?;
+            export.write(format.parse()?, io::stdout().lock())?;
         }
+        _ => return Err("Unknown command".into()),
     }
 
-    pub fn get_node(&mut self, name: &str) -> NodeIndex {
-        self.add_node(name);
-        *self.nodes.get(name).unwrap()
-    }
-
-    pub fn add_edge(&mut self, parent: &str, child: &str, count: u16) {
-        let parent = self.get_node(parent);
-        let child = self.get_node(child);
+    Ok(())
+}
 
-        self.graph.add_edge(parent, child, count);
+/// Rejects flags the command would ignore.
+fn check_flags(command: Option<&str>, flags: &[&str]) -> Result<(), Box<dyn Error>> {
+    if flags.contains(&"--depth") && flags.contains(&"--exact") {
+        return Err("--exact can't be used with --depth".into());
     }
 
-    pub fn count_parents(&self, name: &str) -> usize {
-        let node = self.nodes[name];
-        let mut found: HashSet<NodeIndex> = HashSet::new();
-        let mut todo = HashSet::new();
-        todo.insert(node);
-
-        while !todo.is_empty() {
-            found.extend(todo.iter());
-            todo = todo
-                .iter()
-                .flat_map(|n| {
-                    self.graph
-                        .edges_directed(*n, Direction::Incoming)
-                        .map(|e| e.source())
-                })
-                .filter(|n| !found.contains(n))
-                .collect();
-        }
-        found.remove(&node);
-        found.len()
+    let allowed: &[&str] = match command {
+        Some("ancestors") => &["--input", "--list"],
+        Some("contents") => &["--input", "--depth", "--exact"],
+        Some("diff") => &[],
+        None | Some("paths" | "cycles" | "lint" | "export") => &["--input"],
+        // Left for the unknown command error.
+        Some(_) => return Ok(()),
+    };
+
+    match flags.iter().find(|flag| !allowed.contains(flag)) {
+        Some(flag) => match command {
+            Some(command) => Err(format!("{} can't be used with {}", flag, command).into()),
+            None => Err(format!("{} can't be used without a command", flag).into()),
+        },
+        None => Ok(()),
     }
+}
 
-    pub fn count_contained(&self, name: &str) -> usize {
-        fn walk_children(
-            index: NodeIndex,
-            graph: &Graph<String, u16, Directed>,
-            map: &mut HashMap<NodeIndex, usize>,
-        ) -> usize {
-            let mut total = 1;
-            for edge in graph.edges_directed(index, Direction::Outgoing) {
-                let weight = edge.weight();
-                let index = edge.target();
-                match map.get(&index) {
-                    Some(count) => total += count * (*weight as usize),
-                    None => {
-                        let count = walk_children(index, &graph, map);
-                        total += count * (*weight as usize);
-                        map.insert(index, count);
-                    }
-                }
-            }
+fn part_one(input: &str) -> Result<usize, Box<dyn Error>> {
+    let target = "shiny gold";
+    let bags = BagGraph::parse(input)?;
 
-            total
-        }
+    Ok(bags.count_parents(target)?)
+}
 
-        let mut map = HashMap::new();
-        let node = self.nodes[name];
-        walk_children(node, &self.graph, &mut map) - 1
-    }
+fn part_two(input: &str) -> Result<usize, Box<dyn Error>> {
+    let target = "shiny gold";
+    let bags = BagGraph::parse(input)?;
 
-    pub fn dot(&self) {
-        println!("{:?}", Dot::new(&self.graph));
-    }
+    Ok(bags.count_contained(target)?)
 }
 
-impl Tree {}
-
 #[cfg(test)]
 mod test {
     use crate::*;
-
-    const INPUT_ONE: &str = "\
-light red bags contain 1 bright white bag, 2 muted yellow bags.
-dark orange bags contain 3 bright white bags, 4 muted yellow bags.
-bright white bags contain 1 shiny gold bag.
-muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.
-shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.
-dark olive bags contain 3 faded blue bags, 4 dotted black bags.
-vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
-faded blue bags contain no other bags.
-dotted black bags contain no other bags.";
-
-    const INPUT_TWO: &str = "\
-shiny gold bags contain 2 dark red bags.
-dark red bags contain 2 dark orange bags.
-dark orange bags contain 2 dark yellow bags.
-dark yellow bags contain 2 dark green bags.
-dark green bags contain 2 dark blue bags.
-dark blue bags contain 2 dark violet bags.
-dark violet bags contain no other bags.";
+    use fixtures::{INPUT_ONE, INPUT_TWO};
 
     #[test]
     fn part_one_works() {
-        let res = part_one(INPUT_ONE);
+        let res = part_one(INPUT_ONE).unwrap();
         assert_eq!(4, res);
     }
 
     #[test]
     fn part_two_works() {
-        let res = part_two(INPUT_TWO);
+        let res = part_two(INPUT_TWO).unwrap();
         assert_eq!(126, res);
     }
+
+    #[test]
+    fn rejects_unused_flags() {
+        let err = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            run(&args).unwrap_err().to_string()
+        };
+
+        assert_eq!(
+            "--exact can't be used with --depth",
+            err(&["contents", "shiny gold", "--depth", "1", "--exact"])
+        );
+        assert_eq!(
+            "--depth can't be used with paths",
+            err(&["paths", "light red", "shiny gold", "--depth", "1"])
+        );
+        assert_eq!(
+            "--list can't be used with contents",
+            err(&["contents", "shiny gold", "--list"])
+        );
+        assert_eq!(
+            "--input can't be used with diff",
+            err(&["--input", "a", "diff", "b", "c"])
+        );
+        assert_eq!("--exact can't be used without a command", err(&["--exact"]));
+        assert_eq!("Unknown command", err(&["--list", "frobnicate"]));
+    }
 }
@@ -0,0 +1,352 @@
+//! Explaining why one bag must hold another.
+
+use crate::{query::QueryError, BagGraph, BagId};
+use std::{collections::HashMap, fmt};
+
+/// A chain of bags, each directly inside the one before, and how many of
+/// the last bag one of the first must hold along this chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BagPath {
+    pub bags: Vec<String>,
+    pub quantity: u64,
+}
+
+impl fmt::Display for BagPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} x {}", self.quantity, self.bags.join(" -> "))
+    }
+}
+
+/// The path accounting for the most bags among `paths`, taking the first of
+/// any ties.
+pub fn heaviest(paths: &[BagPath]) -> Option<&BagPath> {
+    paths.iter().rev().max_by_key(|path| path.quantity)
+}
+
+/// The chains from one bag down to the target that `heaviest_path` keeps.
+struct Chains {
+    quantity: u64,
+    /// The heaviest chain, first in color order among ties.
+    heaviest: Vec<BagId>,
+    /// The first chain in color order, needed when a bag holds none of the
+    /// next one and every chain through it weighs nothing.
+    first: Vec<BagId>,
+}
+
+impl BagGraph {
+    /// The bags that can eventually hold a bag, sorted by color.
+    pub fn ancestors(&self, name: &str) -> Result<Vec<String>, QueryError> {
+        let mut ancestors: Vec<String> = self
+            .ancestors_of(self.find(name)?)
+            .into_iter()
+            .map(|id| self.color(id).to_owned())
+            .collect();
+        ancestors.sort();
+
+        Ok(ancestors)
+    }
+
+    /// Every chain of bags from `outer` down to `target`, in color order.
+    /// The quantities add up to how many `target` bags `outer` holds.
+    pub fn paths(&self, outer: &str, target: &str) -> Result<Vec<BagPath>, QueryError> {
+        self.paths_between(self.find(outer)?, self.find(target)?)
+    }
+
+    /// As `paths`, by bag ID.
+    pub fn paths_between(&self, from: BagId, to: BagId) -> Result<Vec<BagPath>, QueryError> {
+        self.check_acyclic(from)?;
+
+        // Only bags that can hold the target lead anywhere.
+        let mut leads = self.ancestors_of(to);
+        leads.insert(to);
+
+        let mut paths = Vec::new();
+        let mut todo: Vec<(Vec<BagId>, u64)> = vec![(vec![from], 1)];
+        while let Some((path, quantity)) = todo.pop() {
+            let bag = path[path.len() - 1];
+            if bag == to && path.len() > 1 {
+                paths.push(BagPath {
+                    bags: path.iter().map(|id| self.color(*id).to_owned()).collect(),
+                    quantity,
+                });
+                continue;
+            }
+
+            let mut children: Vec<_> = self
+                .held_by(bag)
+                .filter(|(_, child)| leads.contains(child))
+                .collect();
+            // Reversed, so the stack pops them in color order.
+            children.sort_by(|a, b| self.color(b.1).cmp(self.color(a.1)));
+
+            for (count, child) in children {
+                let quantity = quantity
+                    .checked_mul(count)
+                    .ok_or_else(|| QueryError::Overflow(self.color(from).to_owned()))?;
+                let mut path = path.clone();
+                path.push(child);
+                todo.push((path, quantity));
+            }
+        }
+
+        Ok(paths)
+    }
+
+    /// The chain from `outer` down to `target` that accounts for the most
+    /// `target` bags, if there is one. Ties go to the first in color order.
+    pub fn heaviest_path(&self, outer: &str, target: &str) -> Result<Option<BagPath>, QueryError> {
+        self.heaviest_path_between(self.find(outer)?, self.find(target)?)
+    }
+
+    /// As `heaviest_path`, by bag ID.
+    pub fn heaviest_path_between(
+        &self,
+        from: BagId,
+        to: BagId,
+    ) -> Result<Option<BagPath>, QueryError> {
+        self.check_acyclic(from)?;
+        let overflow = || QueryError::Overflow(self.color(from).to_owned());
+
+        let mut leads = self.ancestors_of(to);
+        if from == to || !leads.contains(&from) {
+            return Ok(None);
+        }
+        leads.insert(to);
+
+        // Each bag's chains, once all the bags it holds that lead anywhere
+        // have theirs.
+        let mut chains: HashMap<BagId, Chains> = HashMap::new();
+        chains.insert(
+            to,
+            Chains {
+                quantity: 1,
+                heaviest: vec![to],
+                first: vec![to],
+            },
+        );
+        let mut stack = vec![(from, false)];
+
+        while let Some((bag, expanded)) = stack.pop() {
+            if chains.contains_key(&bag) {
+                continue;
+            }
+            let children = self.held_by(bag).filter(|(_, child)| leads.contains(child));
+            if !expanded {
+                stack.push((bag, true));
+                stack.extend(
+                    children
+                        .filter(|(_, child)| !chains.contains_key(child))
+                        .map(|(_, child)| (child, false)),
+                );
+                continue;
+            }
+
+            let mut heaviest: Option<(u64, &[BagId])> = None;
+            let mut first: Option<&[BagId]> = None;
+            for (count, child) in children {
+                let below = &chains[&child];
+                let quantity = count.checked_mul(below.quantity).ok_or_else(overflow)?;
+                let chain: &[BagId] = if count == 0 {
+                    &below.first
+                } else {
+                    &below.heaviest
+                };
+                if heaviest.is_none_or(|(most, most_chain)| {
+                    quantity > most || (quantity == most && self.before(chain, most_chain))
+                }) {
+                    heaviest = Some((quantity, chain));
+                }
+                if first.is_none_or(|first| self.before(&below.first, first)) {
+                    first = Some(&below.first);
+                }
+            }
+
+            let ((quantity, heaviest), first) = heaviest
+                .zip(first)
+                .expect("every bag left leads to the target");
+            let below = Chains {
+                quantity,
+                heaviest: std::iter::once(bag)
+                    .chain(heaviest.iter().copied())
+                    .collect(),
+                first: std::iter::once(bag).chain(first.iter().copied()).collect(),
+            };
+            chains.insert(bag, below);
+        }
+
+        let from = &chains[&from];
+        Ok(Some(BagPath {
+            bags: from
+                .heaviest
+                .iter()
+                .map(|id| self.color(*id).to_owned())
+                .collect(),
+            quantity: from.quantity,
+        }))
+    }
+
+    /// Whether one chain comes before another in color order.
+    fn before(&self, a: &[BagId], b: &[BagId]) -> bool {
+        let colors = |chain: &[BagId]| chain.iter().map(|id| self.color(*id)).collect::<Vec<_>>();
+        colors(a) < colors(b)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures::INPUT_ONE;
+
+    fn path(bags: &[&str], quantity: u64) -> BagPath {
+        BagPath {
+            bags: bags.iter().map(|b| b.to_string()).collect(),
+            quantity,
+        }
+    }
+
+    #[test]
+    fn lists_ancestors() {
+        let bags = BagGraph::parse(INPUT_ONE).unwrap();
+
+        assert_eq!(
+            vec!["bright white", "dark orange", "light red", "muted yellow"],
+            bags.ancestors("shiny gold").unwrap()
+        );
+        assert!(bags.ancestors("light red").unwrap().is_empty());
+    }
+
+    #[test]
+    fn lists_paths() {
+        let bags = BagGraph::parse(INPUT_ONE).unwrap();
+        let paths = bags.paths("light red", "faded blue").unwrap();
+
+        assert_eq!(
+            vec![
+                path(
+                    &[
+                        "light red",
+                        "bright white",
+                        "shiny gold",
+                        "dark olive",
+                        "faded blue"
+                    ],
+                    3
+                ),
+                path(
+                    &[
+                        "light red",
+                        "bright white",
+                        "shiny gold",
+                        "vibrant plum",
+                        "faded blue"
+                    ],
+                    10
+                ),
+                path(&["light red", "muted yellow", "faded blue"], 18),
+                path(
+                    &[
+                        "light red",
+                        "muted yellow",
+                        "shiny gold",
+                        "dark olive",
+                        "faded blue"
+                    ],
+                    12
+                ),
+                path(
+                    &[
+                        "light red",
+                        "muted yellow",
+                        "shiny gold",
+                        "vibrant plum",
+                        "faded blue"
+                    ],
+                    40
+                ),
+            ],
+            paths
+        );
+        assert_eq!(
+            "18 x light red -> muted yellow -> faded blue",
+            paths[2].to_string()
+        );
+        assert!(bags.paths("faded blue", "light red").unwrap().is_empty());
+        assert!(bags.paths("shiny gold", "shiny gold").unwrap().is_empty());
+    }
+
+    #[test]
+    fn finds_heaviest_path() {
+        let bags = BagGraph::parse(INPUT_ONE).unwrap();
+
+        assert_eq!(
+            Some(path(
+                &[
+                    "light red",
+                    "muted yellow",
+                    "shiny gold",
+                    "vibrant plum",
+                    "faded blue"
+                ],
+                40
+            )),
+            bags.heaviest_path("light red", "faded blue").unwrap()
+        );
+        assert_eq!(
+            Some(path(&["dark orange", "muted yellow", "shiny gold"], 8)),
+            bags.heaviest_path("dark orange", "shiny gold").unwrap()
+        );
+        assert_eq!(
+            None,
+            bags.heaviest_path("faded blue", "shiny gold").unwrap()
+        );
+    }
+
+    #[test]
+    fn heaviest_path_matches_listed_paths() {
+        let zeros = "\
+a x bags contain 0 b y bags.
+b y bags contain 1 c z bag, 5 d w bags.
+c z bags contain 1 t u bag.
+d w bags contain 1 t u bag.
+t u bags contain no other bags.";
+
+        for input in [INPUT_ONE, zeros].iter() {
+            let bags = BagGraph::parse(input).unwrap();
+            let colors: Vec<&str> = bags.bags().map(|id| bags.color(id)).collect();
+            for outer in &colors {
+                for target in &colors {
+                    let paths = bags.paths(outer, target).unwrap();
+                    assert_eq!(
+                        heaviest(&paths).cloned(),
+                        bags.heaviest_path(outer, target).unwrap()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn finds_heaviest_path_without_listing() {
+        let depth = 40;
+        let mut rules = String::new();
+        for i in 0..depth {
+            for bag in ["a", "b"].iter() {
+                rules.push_str(&format!(
+                    "{} {} bags contain 1 a {} bag, 2 b {} bags.\n",
+                    bag,
+                    i,
+                    i + 1,
+                    i + 1
+                ));
+            }
+        }
+        let bags = BagGraph::parse(&rules).unwrap();
+        let path = bags
+            .heaviest_path("a 0", &format!("a {}", depth))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(1 << (depth - 1), path.quantity);
+        assert_eq!(depth + 1, path.bags.len());
+    }
+}
@@ -0,0 +1,112 @@
+//! Errors from looking up bags by color, with suggestions for typos.
+
+use std::{error::Error, fmt};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryError {
+    UnknownBag {
+        color: String,
+        suggestion: Option<String>,
+    },
+    /// A chain of bags that eventually contain themselves.
+    Cycle(Vec<String>),
+    /// Too many bags inside a bag to count.
+    Overflow(String),
+}
+
+impl QueryError {
+    /// An unknown bag error, suggesting the closest of `colors` if any is
+    /// near enough to be a likely typo.
+    pub fn unknown<'a>(color: &str, colors: impl Iterator<Item = &'a str>) -> Self {
+        let max_distance = (color.chars().count() / 3).max(2);
+        let suggestion = colors
+            .map(|c| (edit_distance(color, c), c))
+            .filter(|&(d, _)| d <= max_distance)
+            .min()
+            .map(|(_, c)| c.to_owned());
+
+        QueryError::UnknownBag {
+            color: color.to_owned(),
+            suggestion,
+        }
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::UnknownBag { color, suggestion } => {
+                write!(f, "Query Error: no rule for '{}' bags", color)?;
+                if let Some(s) = suggestion {
+                    write!(f, ", did you mean '{}'?", s)?;
+                }
+                Ok(())
+            }
+            QueryError::Cycle(cycle) => write!(
+                f,
+                "Query Error: bags contain themselves: {}",
+                cycle.join(" -> ")
+            ),
+            QueryError::Overflow(color) => {
+                write!(f, "Query Error: too many bags inside '{}' bags", color)
+            }
+        }
+    }
+}
+
+impl Error for QueryError {}
+
+/// Levenshtein distance between two strings, by character.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = prev[j] + if ca == *cb { 0 } else { 1 };
+            row.push(substitute.min(prev[j + 1] + 1).min(row[j] + 1));
+        }
+        prev = row;
+    }
+
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(0, edit_distance("shiny gold", "shiny gold"));
+        assert_eq!(1, edit_distance("shiny gold", "shiny golf"));
+        assert_eq!(2, edit_distance("shiny gold", "shny gld"));
+        assert_eq!(3, edit_distance("kitten", "sitting"));
+        assert_eq!(4, edit_distance("", "gold"));
+    }
+
+    #[test]
+    fn suggests_close_colors() {
+        let colors = ["shiny gold", "dark olive", "dark orange"];
+
+        assert_eq!(
+            QueryError::UnknownBag {
+                color: "dark oliv".into(),
+                suggestion: Some("dark olive".into())
+            },
+            QueryError::unknown("dark oliv", colors.iter().copied())
+        );
+        assert_eq!(
+            QueryError::UnknownBag {
+                color: "mauve".into(),
+                suggestion: None
+            },
+            QueryError::unknown("mauve", colors.iter().copied())
+        );
+        assert_eq!(
+            "Query Error: no rule for 'shiny gld' bags, did you mean 'shiny gold'?",
+            QueryError::unknown("shiny gld", colors.iter().copied()).to_string()
+        );
+    }
+}
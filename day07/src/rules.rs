@@ -0,0 +1,199 @@
+//! Parsing bag rules.
+//!
+//! Each non-blank line is one rule:
+//!
+//! ```text
+//! rule     = color " bag" ["s"] " contain" ["s"] " " contents ["."]
+//! contents = "no other bags" | item { ", " item }
+//! item     = count " " color " bag" ["s"]
+//! ```
+//!
+//! "bag" and "bags" are accepted whatever the count.
+
+use std::{error::Error, fmt};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    /// The 1-based line the rule was read from.
+    pub line: usize,
+    pub color: String,
+    pub contents: Vec<(u64, String)>,
+}
+
+pub fn parse_rules(input: &str) -> Result<Vec<Rule>, ParseRuleError> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| match parse_rule(line.trim()) {
+            Ok((color, contents)) => Ok(Rule {
+                line: i + 1,
+                color,
+                contents,
+            }),
+            Err(kind) => Err(ParseRuleError { line: i + 1, kind }),
+        })
+        .collect()
+}
+
+type Contents = Vec<(u64, String)>;
+
+fn parse_rule(line: &str) -> Result<(String, Contents), RuleErrorKind> {
+    let (bag, contents) = line
+        .split_once(" contains ")
+        .or_else(|| line.split_once(" contain "))
+        .ok_or(RuleErrorKind::MissingContain)?;
+    let color = parse_color(strip_bag(bag)?)?;
+
+    let contents = contents.trim().trim_end_matches('.');
+    let contents = if strip_bag(contents) == Ok("no other") {
+        Vec::new()
+    } else {
+        contents
+            .split(", ")
+            .map(parse_item)
+            .collect::<Result<_, _>>()?
+    };
+
+    Ok((color, contents))
+}
+
+fn parse_item(item: &str) -> Result<(u64, String), RuleErrorKind> {
+    let item = strip_bag(item.trim())?;
+    let (count, color) = item
+        .split_once(' ')
+        .ok_or_else(|| RuleErrorKind::MissingCount(item.to_owned()))?;
+    let count = count
+        .parse::<u64>()
+        .map_err(|e| RuleErrorKind::BadCount(count.to_owned(), e.to_string()))?;
+
+    Ok((count, parse_color(color)?))
+}
+
+fn strip_bag(s: &str) -> Result<&str, RuleErrorKind> {
+    s.strip_suffix(" bags")
+        .or_else(|| s.strip_suffix(" bag"))
+        .ok_or_else(|| RuleErrorKind::MissingBag(s.to_owned()))
+}
+
+fn parse_color(color: &str) -> Result<String, RuleErrorKind> {
+    let color = color.trim();
+    if color.is_empty() {
+        Err(RuleErrorKind::EmptyColor)
+    } else {
+        Ok(color.to_owned())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseRuleError {
+    pub line: usize,
+    pub kind: RuleErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleErrorKind {
+    MissingContain,
+    /// A bag description that doesn't end in "bag" or "bags".
+    MissingBag(String),
+    MissingCount(String),
+    /// The count, and why it couldn't be parsed.
+    BadCount(String, String),
+    EmptyColor,
+}
+
+impl fmt::Display for ParseRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use RuleErrorKind::*;
+
+        write!(f, "Parse Rule Error on line {}: ", self.line)?;
+        match &self.kind {
+            MissingContain => write!(f, "expected 'contain'"),
+            MissingBag(s) => write!(f, "expected 'bag' or 'bags' after '{}'", s),
+            MissingCount(s) => write!(f, "expected a count before '{}'", s),
+            BadCount(s, e) => write!(f, "bad count '{}': {}", s, e),
+            EmptyColor => write!(f, "missing bag color"),
+        }
+    }
+}
+
+impl Error for ParseRuleError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn error(input: &str) -> ParseRuleError {
+        parse_rules(input).unwrap_err()
+    }
+
+    #[test]
+    fn parses_rules() {
+        let rules = parse_rules(
+            "\
+light red bags contain 1 bright white bag, 2 muted yellow bags.
+
+faded blue bags contain no other bags.
+dotted black bag contains 1 faded blue bags, 70000 shiny gold bag",
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                Rule {
+                    line: 1,
+                    color: "light red".into(),
+                    contents: vec![(1, "bright white".into()), (2, "muted yellow".into())],
+                },
+                Rule {
+                    line: 3,
+                    color: "faded blue".into(),
+                    contents: vec![],
+                },
+                Rule {
+                    line: 4,
+                    color: "dotted black".into(),
+                    contents: vec![(1, "faded blue".into()), (70000, "shiny gold".into())],
+                },
+            ],
+            rules
+        );
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        let ok = "faded blue bags contain no other bags.\n";
+
+        assert_eq!(
+            ParseRuleError {
+                line: 2,
+                kind: RuleErrorKind::MissingContain
+            },
+            error(&format!("{}light red bags hold 1 bright white bag.", ok))
+        );
+        assert_eq!(
+            RuleErrorKind::MissingBag("light red".into()),
+            error("light red contain 1 bright white bag.").kind
+        );
+        assert_eq!(
+            RuleErrorKind::MissingBag("1 bright white".into()),
+            error("light red bags contain 1 bright white.").kind
+        );
+        assert_eq!(
+            RuleErrorKind::MissingCount("white".into()),
+            error("light red bags contain white bags.").kind
+        );
+        assert!(matches!(
+            error("light red bags contain x bright white bags.").kind,
+            RuleErrorKind::BadCount(..)
+        ));
+        assert!(matches!(
+            error("light red bags contain 99999999999999999999 bright white bags.").kind,
+            RuleErrorKind::BadCount(..)
+        ));
+        assert_eq!(
+            RuleErrorKind::EmptyColor,
+            error("light red bags contain 1  bags.").kind
+        );
+    }
+}
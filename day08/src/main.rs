@@ -1,158 +1,49 @@
 //! --- Day 8: Handheld Halting ---
 //! https://adventofcode.com/2020/day/8
 
-use std::{collections::HashSet, error::Error, fmt, num::ParseIntError, str::FromStr};
+use handheld::{Outcome, Program, Vm};
+use std::{error::Error, process};
 
 const INPUT: &str = include_str!("../../inputs/day08.txt");
 
 fn main() {
-    println!("Part one: {}", part_one(INPUT));
-    println!("Part two: {}", part_two(INPUT));
-}
-
-fn part_one(input: &str) -> i64 {
-    let mut acc = 0;
-    let mut seen = HashSet::new();
-    let instructions = input
-        .lines()
-        .map(|s| Instruction::from_str(s).unwrap())
-        .collect::<Vec<Instruction>>();
-
-    let mut i = 0;
-    while i < instructions.len() {
-        if seen.contains(&i) {
-            break;
-        }
-        seen.insert(i);
-
-        match instructions[i] {
-            Instruction::Nop(_) => i += 1,
-            Instruction::Acc(n) => {
-                acc += n;
-                i += 1;
-            }
-            Instruction::Jmp(n) => {
-                i = (i as i64 + n) as usize;
-            }
-        }
+    if let Err(e) = run() {
+        eprintln!("{}", e);
+        process::exit(1);
     }
-
-    acc
 }
 
-fn part_two(input: &str) -> i64 {
-    let mut total = 0;
-    let mut changed = HashSet::new();
-    let mut ends = false;
-    let instructions = input
-        .lines()
-        .map(|s| Instruction::from_str(s).unwrap())
-        .collect::<Vec<Instruction>>();
-
-    while !ends {
-        let mut acc = 0;
-        let mut seen = HashSet::new();
-        let mut has_changed = false;
-        let mut i = 0;
-        loop {
-            if seen.contains(&i) {
-                break;
-            }
-            seen.insert(i);
-
-            match instructions[i] {
-                Instruction::Nop(n) => {
-                    if !has_changed && !changed.contains(&i) && n != 0 {
-                        changed.insert(i);
-                        has_changed = true;
-                        i = (i as i64 + n) as usize;
-                    } else {
-                        i += 1;
-                    }
-                }
-                Instruction::Acc(n) => {
-                    acc += n;
-                    i += 1;
-                }
-                Instruction::Jmp(n) => {
-                    if !has_changed && !changed.contains(&i) {
-                        changed.insert(i);
-                        has_changed = true;
-                        i += 1;
-                    } else {
-                        i = (i as i64 + n) as usize;
-                    }
-                }
-            }
-            if i >= instructions.len() {
-                ends = true;
-                total = acc;
-                break;
-            }
-        }
-    }
-
-    total
-}
+fn run() -> Result<(), Box<dyn Error>> {
+    println!("Part one: {}", part_one(INPUT)?);
+    println!("Part two: {}", part_two(INPUT)?);
 
-#[derive(Debug, Clone)]
-enum Instruction {
-    Nop(i64),
-    Acc(i64),
-    Jmp(i64),
+    Ok(())
 }
 
-impl FromStr for Instruction {
-    type Err = ParseInstructionError;
+/// The accumulator just before the program repeats an instruction.
+fn part_one(input: &str) -> Result<i64, Box<dyn Error>> {
+    let program: Program = input.parse()?;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.trim().split(' ');
-
-        let ops = parts.next().to_owned();
-        match ops {
-            Some(ops) => {
-                let num = parts.next().unwrap().parse::<i64>()?;
-                match ops {
-                    "nop" => Ok(Instruction::Nop(num)),
-                    "acc" => Ok(Instruction::Acc(num)),
-                    "jmp" => Ok(Instruction::Jmp(num)),
-                    _ => Err(ParseInstructionError::Other(
-                        "Failed to parse operation".into(),
-                    )),
-                }
-            }
-            None => Err(ParseInstructionError::Other(
-                "Failed to parse operation".into(),
-            )),
-        }
+    match Vm::new(&program).run() {
+        Outcome::Looped { acc, .. } => Ok(acc),
+        outcome => Err(format!("Expected the program to loop, but got {:?}", outcome).into()),
     }
 }
 
-#[derive(Debug)]
-enum ParseInstructionError {
-    Other(String),
-    WrappedErr(Box<dyn Error>),
+/// The accumulator after the program halts, once the one `nop` or `jmp`
+/// that stops it looping has been swapped.
+fn part_two(input: &str) -> Result<i64, Box<dyn Error>> {
+    let program: Program = input.parse()?;
+
+    (0..program.len())
+        .filter_map(|pc| program.repaired(pc))
+        .find_map(|repaired| match Vm::new(&repaired).run() {
+            Outcome::Halted { acc } => Some(acc),
+            _ => None,
+        })
+        .ok_or_else(|| "No single swap makes the program halt".into())
 }
 
-impl From<ParseIntError> for ParseInstructionError {
-    fn from(error: ParseIntError) -> Self {
-        ParseInstructionError::WrappedErr(Box::new(error))
-    }
-}
-
-impl fmt::Display for ParseInstructionError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        use ParseInstructionError::*;
-
-        match self {
-            Other(s) => write!(f, "Parse Instuction Error: {}", s),
-            WrappedErr(e) => write!(f, "Parse Instuction Error: {}", e),
-        }
-    }
-}
-
-impl Error for ParseInstructionError {}
-
 #[cfg(test)]
 mod test {
     use crate::*;
@@ -170,13 +61,13 @@ acc +6";
 
     #[test]
     fn part_one_works() {
-        let res = part_one(INPUT);
+        let res = part_one(INPUT).unwrap();
         assert_eq!(5, res);
     }
 
     #[test]
     fn part_two_works() {
-        let res = part_two(INPUT);
+        let res = part_two(INPUT).unwrap();
         assert_eq!(8, res);
     }
 }
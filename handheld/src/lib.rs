@@ -0,0 +1,8 @@
+//! The handheld game console's boot code: parsing programs and running
+//! them one instruction at a time.
+
+mod program;
+mod vm;
+
+pub use program::{Instruction, ParseInstructionError, ParseProgramError, Program};
+pub use vm::{Event, Outcome, Vm};
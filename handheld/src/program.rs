@@ -0,0 +1,223 @@
+//! Instructions and the programs made of them.
+
+use std::{error::Error, fmt, num::ParseIntError, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    Nop(i64),
+    Acc(i64),
+    Jmp(i64),
+}
+
+impl FromStr for Instruction {
+    type Err = ParseInstructionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().split(' ');
+
+        let ops = parts.next().to_owned();
+        match ops {
+            Some(ops) => {
+                let num = parts
+                    .next()
+                    .ok_or_else(|| ParseInstructionError::Other("Missing argument".into()))?
+                    .parse::<i64>()?;
+                if let Some(extra) = parts.next() {
+                    return Err(ParseInstructionError::Other(format!(
+                        "Unexpected '{}' after argument",
+                        extra
+                    )));
+                }
+                match ops {
+                    "nop" => Ok(Instruction::Nop(num)),
+                    "acc" => Ok(Instruction::Acc(num)),
+                    "jmp" => Ok(Instruction::Jmp(num)),
+                    _ => Err(ParseInstructionError::Other(
+                        "Failed to parse operation".into(),
+                    )),
+                }
+            }
+            None => Err(ParseInstructionError::Other(
+                "Failed to parse operation".into(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseInstructionError {
+    Other(String),
+    BadArgument(ParseIntError),
+}
+
+impl From<ParseIntError> for ParseInstructionError {
+    fn from(error: ParseIntError) -> Self {
+        ParseInstructionError::BadArgument(error)
+    }
+}
+
+impl fmt::Display for ParseInstructionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ParseInstructionError::*;
+
+        match self {
+            Other(s) => write!(f, "Parse Instruction Error: {}", s),
+            BadArgument(e) => write!(f, "Parse Instruction Error: {}", e),
+        }
+    }
+}
+
+impl Error for ParseInstructionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseInstructionError::BadArgument(e) => Some(e),
+            ParseInstructionError::Other(_) => None,
+        }
+    }
+}
+
+/// A list of instructions, one per line of source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    instructions: Vec<Instruction>,
+}
+
+impl Program {
+    pub fn new(instructions: Vec<Instruction>) -> Self {
+        Program { instructions }
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    pub fn get(&self, pc: usize) -> Option<Instruction> {
+        self.instructions.get(pc).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    /// A copy with the instruction at `pc` swapped between `nop` and `jmp`,
+    /// or `None` if it's neither.
+    pub fn repaired(&self, pc: usize) -> Option<Program> {
+        let swapped = match self.get(pc)? {
+            Instruction::Nop(n) => Instruction::Jmp(n),
+            Instruction::Jmp(n) => Instruction::Nop(n),
+            Instruction::Acc(_) => return None,
+        };
+        let mut program = self.clone();
+        program.instructions[pc] = swapped;
+        Some(program)
+    }
+}
+
+impl FromStr for Program {
+    type Err = ParseProgramError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                line.parse()
+                    .map_err(|error| ParseProgramError { line: i + 1, error })
+            })
+            .collect::<Result<_, _>>()
+            .map(Program::new)
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseProgramError {
+    /// The 1-based line of the instruction that couldn't be parsed.
+    pub line: usize,
+    pub error: ParseInstructionError,
+}
+
+impl fmt::Display for ParseProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.error)
+    }
+}
+
+impl Error for ParseProgramError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_programs() {
+        let program: Program = "nop +0\nacc -99\n\njmp +4\n".parse().unwrap();
+
+        assert_eq!(
+            &[
+                Instruction::Nop(0),
+                Instruction::Acc(-99),
+                Instruction::Jmp(4)
+            ],
+            program.instructions()
+        );
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        let err = "nop +0\nacc\n".parse::<Program>().unwrap_err();
+        assert_eq!(2, err.line);
+        assert_eq!(
+            "Line 2: Parse Instruction Error: Missing argument",
+            err.to_string()
+        );
+
+        assert_eq!(
+            3,
+            "nop +0\nacc +1\nhop +2"
+                .parse::<Program>()
+                .unwrap_err()
+                .line
+        );
+        assert_eq!(1, "jmp x".parse::<Program>().unwrap_err().line);
+        assert!(matches!(
+            "jmp x".parse::<Instruction>(),
+            Err(ParseInstructionError::BadArgument(_))
+        ));
+        assert_eq!(
+            Err(ParseInstructionError::Other(
+                "Unexpected 'junk' after argument".into()
+            )),
+            "nop +0 junk".parse::<Instruction>()
+        );
+    }
+
+    #[test]
+    fn errors_can_cross_threads() {
+        let err: Box<dyn Error + Send + Sync> = Box::new("acc".parse::<Program>().unwrap_err());
+        assert!(std::thread::spawn(move || err.to_string()).join().is_ok());
+    }
+
+    #[test]
+    fn repairs_one_instruction() {
+        let program: Program = "nop +2\nacc +1\njmp -1".parse().unwrap();
+
+        assert_eq!(
+            Some(Instruction::Jmp(2)),
+            program.repaired(0).unwrap().get(0)
+        );
+        assert_eq!(None, program.repaired(1));
+        assert_eq!(
+            Some(Instruction::Nop(-1)),
+            program.repaired(2).unwrap().get(2)
+        );
+        assert_eq!(None, program.repaired(3));
+    }
+}
@@ -0,0 +1,189 @@
+//! Running programs.
+
+use crate::{Instruction, Program};
+use std::convert::TryFrom;
+
+/// What happened on one step of a `Vm`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    /// Ran the instruction that was at `pc`.
+    Ran { pc: usize, instruction: Instruction },
+    /// Reached the end of the program, just past the last instruction.
+    Halted,
+    /// The program counter is past the end of the program, or the
+    /// instruction at it jumps out of range or overflows the accumulator.
+    Fault { pc: usize },
+}
+
+/// How a run of a `Vm` ended, with the accumulator at that point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Halted {
+        acc: i64,
+    },
+    /// About to run the instruction at `pc` a second time.
+    Looped {
+        pc: usize,
+        acc: i64,
+    },
+    Faulted {
+        pc: usize,
+        acc: i64,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Vm<'a> {
+    program: &'a Program,
+    pc: usize,
+    acc: i64,
+}
+
+impl<'a> Vm<'a> {
+    pub fn new(program: &'a Program) -> Self {
+        Vm {
+            program,
+            pc: 0,
+            acc: 0,
+        }
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn acc(&self) -> i64 {
+        self.acc
+    }
+
+    /// Runs a single instruction. Halting or faulting leaves the state as
+    /// it was, so stepping again gives the same event.
+    pub fn step(&mut self) -> Event {
+        let pc = self.pc;
+        let instruction = match self.program.get(pc) {
+            Some(instruction) => instruction,
+            None if pc == self.program.len() => return Event::Halted,
+            None => return Event::Fault { pc },
+        };
+
+        match instruction {
+            Instruction::Nop(_) => self.pc += 1,
+            Instruction::Acc(n) => match self.acc.checked_add(n) {
+                Some(acc) => {
+                    self.acc = acc;
+                    self.pc += 1;
+                }
+                None => return Event::Fault { pc },
+            },
+            Instruction::Jmp(n) => match jump(pc, n) {
+                Some(target) => self.pc = target,
+                None => return Event::Fault { pc },
+            },
+        }
+
+        Event::Ran { pc, instruction }
+    }
+
+    /// Steps until the program halts, faults, or is about to repeat an
+    /// instruction, which it would then do forever.
+    pub fn run(&mut self) -> Outcome {
+        let mut seen = vec![false; self.program.len()];
+
+        loop {
+            if seen.get(self.pc) == Some(&true) {
+                return Outcome::Looped {
+                    pc: self.pc,
+                    acc: self.acc,
+                };
+            }
+            match self.step() {
+                Event::Ran { pc, .. } => seen[pc] = true,
+                Event::Halted => return Outcome::Halted { acc: self.acc },
+                Event::Fault { pc } => return Outcome::Faulted { pc, acc: self.acc },
+            }
+        }
+    }
+}
+
+fn jump(pc: usize, offset: i64) -> Option<usize> {
+    let target = i64::try_from(pc).ok()?.checked_add(offset)?;
+    usize::try_from(target).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = "\
+nop +0
+acc +1
+jmp +4
+acc +3
+jmp -3
+acc -99
+acc +1
+jmp -4
+acc +6";
+
+    #[test]
+    fn steps() {
+        let program: Program = INPUT.parse().unwrap();
+        let mut vm = Vm::new(&program);
+
+        assert_eq!(
+            Event::Ran {
+                pc: 0,
+                instruction: Instruction::Nop(0)
+            },
+            vm.step()
+        );
+        vm.step();
+        assert_eq!((2, 1), (vm.pc(), vm.acc()));
+        vm.step();
+        assert_eq!(6, vm.pc());
+    }
+
+    #[test]
+    fn detects_loops() {
+        let program: Program = INPUT.parse().unwrap();
+        assert_eq!(Outcome::Looped { pc: 1, acc: 5 }, Vm::new(&program).run());
+    }
+
+    #[test]
+    fn halts() {
+        let program: Program = INPUT.parse().unwrap();
+        let program = program.repaired(7).unwrap();
+        let mut vm = Vm::new(&program);
+
+        assert_eq!(Outcome::Halted { acc: 8 }, vm.run());
+        assert_eq!(Event::Halted, vm.step());
+    }
+
+    #[test]
+    fn faults_outside_the_program() {
+        let program: Program = "acc +1\njmp -2".parse().unwrap();
+        assert_eq!(Outcome::Faulted { pc: 1, acc: 1 }, Vm::new(&program).run());
+
+        let program: Program = "acc +1\njmp +2".parse().unwrap();
+        let mut vm = Vm::new(&program);
+        assert_eq!(Outcome::Faulted { pc: 3, acc: 1 }, vm.run());
+        assert_eq!(Event::Fault { pc: 3 }, vm.step());
+    }
+
+    #[test]
+    fn faults_on_overflow() {
+        let program: Program = "acc +9223372036854775807\nacc +1".parse().unwrap();
+        let mut vm = Vm::new(&program);
+        assert_eq!(
+            Outcome::Faulted {
+                pc: 1,
+                acc: i64::MAX
+            },
+            vm.run()
+        );
+        assert_eq!((1, i64::MAX), (vm.pc(), vm.acc()));
+
+        let program: Program = "nop +0\njmp +9223372036854775807".parse().unwrap();
+        assert_eq!(Outcome::Faulted { pc: 1, acc: 0 }, Vm::new(&program).run());
+    }
+}